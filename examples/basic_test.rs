@@ -3,7 +3,13 @@ use std::time::Duration;
 use async_std::task::sleep;
 use bevy::{ ecs::world::{ Command, CommandQueue }, prelude::*, tasks::IoTaskPool };
 
-use bevy_lazy_signals::{ api::LazySignals, LazySignalsPlugin, StaticStrRef };
+use bevy_lazy_signals::{
+    api::LazySignals,
+    handles::{ ActionHandle, EffectHandle, Memo, Signal },
+    params::SignalReader,
+    LazySignalsPlugin,
+    StaticStrRef,
+};
 
 // this example toggles a `loggged_in` value every 10 seconds via an async task, triggering computeds and effects
 
@@ -23,12 +29,12 @@ impl MyExampleAuthResource {
 }
 
 // simple command to toggle the login status of the user
-struct MyToggleLoginCommand(Entity);
+struct MyToggleLoginCommand(Signal<bool>);
 
 impl Command for MyToggleLoginCommand {
     fn apply(self, world: &mut World) {
         info!("Toggling login");
-        if let Some(status) = LazySignals.read::<bool>(self.0, world) {
+        if let Some(status) = LazySignals.read(self.0, world) {
             // it's perfectly ok to return this command in a task's queue
 
             // that could be an infinite loop, but only running once per tick, which may be wanted
@@ -41,14 +47,15 @@ impl Command for MyToggleLoginCommand {
     }
 }
 
-// this just keeps track of all the `LazySignals` primitives. just need the entity.
+// this just keeps track of all the `LazySignals` primitives. just need the handle (or the entity,
+// if the data types are mixed, since every handle converts into its entity)
 #[derive(Resource, Default)]
 struct MyTestResource {
-    pub action: Vec<Entity>,
-    pub computed: Vec<Entity>,
-    pub effect: Vec<Entity>,
+    pub action: Vec<ActionHandle>,
+    pub computed: Vec<Memo<StaticStrRef>>,
+    pub effect: Vec<EffectHandle>,
     pub signal: Vec<Entity>,
-    pub trigger: Vec<Entity>,
+    pub trigger: Vec<Signal<()>>,
 }
 
 // concrete tuple type to safely work with the `DynamicTuple` coming out of the `LazySignals` systems
@@ -95,12 +102,12 @@ fn init(mut test: ResMut<MyTestResource>, mut commands: Commands) {
 
     // leave signals and computeds as local values to use as deps throughout the init system
     // since we can't move the deps into our closures from the test resource
-    test.signal.push(signal0);
+    test.signal.push(signal0.into());
     info!("created test signal 0, entity {}", test.signal[0]);
 
     // for strings the only thing I've gotten to work so far is `&'static str`
     let signal1 = LazySignals.state("Congrats, you logged in somehow", &mut commands);
-    test.signal.push(signal1);
+    test.signal.push(signal1.into());
     info!("created test signal 1, entity {}", test.signal[1]);
 
     // for an effect trigger, we don't care about the value, only that the trigger signal was sent
@@ -131,7 +138,7 @@ fn init(mut test: ResMut<MyTestResource>, mut commands: Commands) {
     // TODO make sure send_and_trigger works the way we think it does
     let trigger0 = LazySignals.state((), &mut commands);
     test.trigger.push(trigger0);
-    info!("created test trigger 0, entity {}", test.trigger[0]);
+    info!("created test trigger 0, entity {}", test.trigger[0].entity());
 
     // simple effect that logs its `sources` whenever one changes or it is triggered
     let log_logins = |args: MyClosureArgs, world: &mut World| {
@@ -166,16 +173,16 @@ fn init(mut test: ResMut<MyTestResource>, mut commands: Commands) {
             // closure to call when the effect is triggered
             log_logins,
             // type of each source must match type at same tuple position
-            // it's not unsafe(?); it just won't work if we screw this up
-            // TODO definitely think about that some more
-            vec![signal0, signal1], // sending either signal triggers the effect
+            // a tuple of handles is checked by the compiler (a `Vec<Entity>` is only checked when
+            // the effect is created, and faults it if the types do not line up)
+            (signal0, signal1), // sending either signal triggers the effect
             // explicit triggers are not added to the args tuple like sources are
             Vec::<Entity>::new(),
             &mut commands
         )
     );
 
-    info!("created test effect 0, entity {}", test.effect[0]);
+    info!("created test effect 0, entity {}", test.effect[0].entity());
 
     // simple closure that shows a supplied value or an error message
 
@@ -209,11 +216,11 @@ fn init(mut test: ResMut<MyTestResource>, mut commands: Commands) {
     // `MyClosureArgs` == args tuple type, `StaticStrRef (&'static str)` == return type
     let computed0 = LazySignals.computed::<MyAuthArgs, StaticStrRef>(
         derive_login_msg,
-        vec![signal0, signal1], // sending either signal triggers a recompute
+        (signal0, signal1), // sending either signal triggers a recompute
         &mut commands
     );
     test.computed.push(computed0);
-    info!("created test computed 0, entity {}", test.computed[0]);
+    info!("created test computed 0, entity {}", test.computed[0].entity());
 
     // simple computed to store a string value from a computed, or an error, depending on the bool
     let computed1 = LazySignals.computed::<MyAuthArgs, StaticStrRef>(
@@ -232,11 +239,11 @@ fn init(mut test: ResMut<MyTestResource>, mut commands: Commands) {
             info!("COMPUTED1 value: {}", value);
             LazySignals::result(value)
         },
-        vec![signal0, computed0],
+        (signal0, computed0),
         &mut commands
     );
     test.computed.push(computed1);
-    info!("created test computed 1, entity {}", test.computed[1]);
+    info!("created test computed 1, entity {}", test.computed[1].entity());
 
     // set this one up to get the msg from a memo instead of a signal
     test.effect.push(
@@ -258,12 +265,12 @@ fn init(mut test: ResMut<MyTestResource>, mut commands: Commands) {
                 // example effect system
                 LazySignals.box_system(effect_system)
             },
-            vec![signal0, computed1],
+            (signal0, computed1),
             Vec::<Entity>::new(),
             &mut commands
         )
     );
-    info!("created test effect 1, entity {}", test.effect[1]);
+    info!("created test effect 1, entity {}", test.effect[1].entity());
 
     // set up a long-running async task with `triggers` only and no `sources` (pass in unit type)
 
@@ -305,14 +312,14 @@ fn init(mut test: ResMut<MyTestResource>, mut commands: Commands) {
                     command_queue
                 })
             },
-            Vec::<Entity>::new(),
+            (),
             // triggering a signal will run effects without passing the signal's value as a param
             // (it still sends the value of the `sources` as usual, although this task has none)
-            vec![trigger0],
+            vec![trigger0.into()],
             &mut commands
         )
     );
-    info!("created test task 0, entity {}", test.action[0]);
+    info!("created test task 0, entity {}", test.action[0].entity());
 
     info!("init complete");
 }
@@ -329,18 +336,19 @@ fn send_some_signals(test: Res<MyTestResource>, mut commands: Commands) {
     LazySignals.trigger(test.trigger[0], &mut commands);
 }
 
+// no exclusive world access needed to read signals, so this can run in parallel with other systems
 fn status(
-    world: &World,
     example_auth_resource: Res<MyExampleAuthResource>,
+    logins: SignalReader<bool>,
     test: Res<MyTestResource>
 ) {
     trace!("logged in: {}", example_auth_resource.is_logged_in());
 
-    if let Some(value) = LazySignals.read::<bool>(test.signal[0], world) {
+    if let Some(value) = logins.read(test.signal[0]) {
         trace!("value: {}", value);
     }
 
-    if let Some(error) = LazySignals.get_error::<bool>(test.signal[0], world) {
+    if let Some(error) = logins.get_error(test.signal[0]) {
         error!("error: {}", error);
     }
 }
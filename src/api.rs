@@ -1,12 +1,20 @@
-use std::sync::Mutex;
+use std::sync::{ Arc, Mutex };
 
-use bevy::{ ecs::system::BoxedSystem, prelude::* };
+use bevy::{
+    ecs::{ system::BoxedSystem, world::CommandQueue },
+    prelude::*,
+    reflect::DynamicTuple,
+    tasks::AsyncComputeTaskPool,
+};
 
 use crate::{
-    arcane_wizardry::make_tuple,
-    commands::LazySignalsCommandsExt,
+    arcane_wizardry::{ copy_tuple, make_tuple, track_sources },
+    commands::{ LazySignalsCommandsExt, SendSignalCommand },
     framework::*,
+    handles::*,
     lazy_immutable::{ LazySignalsImmutable, LazySignalsState },
+    lazy_stream::LazySignalsStream,
+    signal_stream::SignalStream,
 };
 
 /// This is the reference user API, patterned after the TC39 proposal.
@@ -16,23 +24,91 @@ pub fn make_effect_with<P: LazySignalsArgs>(
     Mutex::new(
         Box::new(move |tuple, world| {
             trace!("-running effect context with args {:?}", tuple);
-            closure(make_tuple::<P>(tuple), world)
+            match make_tuple::<P>(tuple) {
+                Ok(args) => closure(args, world),
+                Err(error) => {
+                    error!("ERROR running effect: {}", error);
+                    None
+                }
+            }
         })
     )
 }
 
+pub fn make_effect_system_with<P: LazySignalsArgs, M>(
+    system: impl IntoSystem<P, (), M>
+) -> BoxedSystem<DynamicTuple, ()> {
+    // convert the args before handing them to the system, which only knows the concrete tuple
+    // (it is only run, and initialized the first time, if they fit)
+    let mut system: BoxedSystem<P, ()> = Box::new(IntoSystem::into_system(system));
+    let mut initialized = false;
+    let run = move |In(tuple): In<DynamicTuple>, world: &mut World| {
+        trace!("-running effect system with args {:?}", tuple);
+        let args = match make_tuple::<P>(tuple) {
+            Ok(args) => args,
+            Err(error) => {
+                error!("ERROR running effect system: {}", error);
+                return;
+            }
+        };
+        if !initialized {
+            system.initialize(world);
+            initialized = true;
+        }
+        system.run(args, world);
+        system.apply_deferred(world);
+    };
+    Box::new(IntoSystem::into_system(run))
+}
+
 pub fn make_computed_with<P: LazySignalsArgs, R: LazySignalsData>(
     closure: impl Computed<P, R>
 ) -> Mutex<Box<dyn ComputedContext>> {
     Mutex::new(
-        Box::new(move |tuple, entity, world| {
+        Box::new(move |tuple, _upstream_error, _world| {
             trace!("-running computed context with args {:?}", tuple);
-            let result = closure(make_tuple::<P>(tuple));
-            if let Some(error) = result.error {
+            let result = make_tuple::<P>(tuple).map_or_else(LazySignals::error, &closure);
+            if let Some(error) = &result.error {
                 // TODO process errors
                 error!("ERROR running computed: {}", error.to_string());
             }
-            store_result::<R>(result, entity, world)
+            store_later::<R>(result)
+        })
+    )
+}
+
+pub fn make_computed_propagating_with<P: LazySignalsArgs, R: LazySignalsData>(
+    closure: impl Computed<P, R>
+) -> Mutex<Box<dyn ComputedContext>> {
+    Mutex::new(
+        Box::new(move |tuple, upstream_error, _world| {
+            // pass any upstream error through instead of running the closure
+            if let Some(error) = upstream_error {
+                trace!("-passing through upstream error: {}", error);
+                return store_later::<R>(LazySignals::error(error));
+            }
+
+            trace!("-running computed context with args {:?}", tuple);
+            let result = make_tuple::<P>(tuple).map_or_else(LazySignals::error, &closure);
+            store_later::<R>(result)
+        })
+    )
+}
+
+pub fn make_computed_tracked_with<R: LazySignalsData>(
+    closure: impl TrackedComputed<R>
+) -> Mutex<Box<dyn ComputedContext>> {
+    Mutex::new(
+        Box::new(move |_tuple, _upstream_error, world| {
+            trace!("-running tracked computed context");
+            let mut context = TrackingContext::new(world);
+            let result = closure(&mut context);
+            let reads = context.into_reads();
+            Box::new(move |entity: &Entity, world: &mut World| {
+                let changed = store_result::<R>(result, entity, world);
+                track_sources(entity, reads, world);
+                changed
+            })
         })
     )
 }
@@ -43,11 +119,56 @@ pub fn make_action_with<P: LazySignalsArgs>(
     Mutex::new(
         Box::new(move |tuple| {
             trace!("-running task context with args {:?}", tuple);
-            closure(make_tuple::<P>(tuple))
+            match copy_tuple::<P>(tuple) {
+                Ok(args) => {
+                    let task = closure(args);
+                    Box::pin(async move { Ok(task.await) })
+                }
+                Err(error) => Box::pin(async move { Err(error) }),
+            }
         })
     )
 }
 
+pub fn make_fallible_action_with<P: LazySignalsArgs>(
+    closure: impl FallibleAction<P>
+) -> Mutex<Box<dyn ActionWrapper>> {
+    Mutex::new(
+        Box::new(move |tuple| {
+            trace!("-running fallible task context with args {:?}", tuple);
+            match copy_tuple::<P>(tuple) {
+                Ok(args) => Box::pin(closure(args)),
+                Err(error) => Box::pin(async move { Err(error) }),
+            }
+        })
+    )
+}
+
+pub fn make_reducer_with<S: LazySignalsData, M: Send + Sync + 'static>(
+    reducer: impl Reducer<S, M>
+) -> Arc<dyn ReducerWrapper<M>> {
+    let reducer = Arc::new(reducer);
+    Arc::new(move |message, entity| {
+        trace!("-dispatching message to reducer {:?}", entity.id());
+        let id = entity.id();
+        let reducer = reducer.clone();
+        if let Some(mut state) = entity.get_mut::<LazySignalsState<S>>() {
+            // fold the message into whatever the state will be by the time it is sent
+            state.merge_update(
+                Box::new(move |data: Option<&S>| {
+                    let Some(data) = data else {
+                        // there is nothing to fold it into (e.g. the state holds an error instead)
+                        warn!("reducer {:?} has no data, so the message was dropped", id);
+                        return None;
+                    };
+                    Some(reducer(data, message))
+                })
+            );
+            entity.insert(SendSignal);
+        }
+    })
+}
+
 /// Convenience function to store a result in an entity.
 pub fn store_result<T: LazySignalsData>(
     data: LazySignalsResult<T>,
@@ -59,6 +180,11 @@ pub fn store_result<T: LazySignalsData>(
     component.update(data)
 }
 
+/// Convenience function to defer storing a result until the world is available again.
+pub fn store_later<T: LazySignalsData>(data: LazySignalsResult<T>) -> Box<dyn ComputedStore> {
+    Box::new(move |entity: &Entity, world: &mut World| store_result::<T>(data, entity, world))
+}
+
 /// ## Main Signal primitive factory.
 /// Convenience functions for `Signal` creation and manipulation inspired by the TC39 proposal.
 pub struct LazySignals;
@@ -67,13 +193,57 @@ impl LazySignals {
     pub fn action<P: LazySignalsArgs>(
         &self,
         task_closure: impl Action<P>,
-        sources: Vec<Entity>,
+        sources: impl LazySignalsSources<P>,
         triggers: Vec<Entity>,
         commands: &mut Commands
-    ) -> Entity {
+    ) -> ActionHandle {
         let entity = commands.spawn_empty().id();
         commands.create_action::<P>(entity, make_action_with(task_closure), sources, triggers);
-        entity
+        ActionHandle::from_entity(entity)
+    }
+
+    /// Create an `Action` like `action`, with options such as what to do when it is triggered while
+    /// its task is still running (see `ActionConcurrency`).
+    pub fn action_with_options<P: LazySignalsArgs>(
+        &self,
+        task_closure: impl Action<P>,
+        sources: impl LazySignalsSources<P>,
+        triggers: Vec<Entity>,
+        options: ActionOptions,
+        commands: &mut Commands
+    ) -> ActionHandle {
+        let action = self.action::<P>(task_closure, sources, triggers, commands);
+        commands.entity(action.entity()).insert(options);
+        action
+    }
+
+    /// Create an `Action` like `action_with_options`, but its task resolves to a `Result`. An error
+    /// counts as a failure, so the task can be retried (see `ActionRetry`).
+    pub fn action_fallible<P: LazySignalsArgs>(
+        &self,
+        task_closure: impl FallibleAction<P>,
+        sources: impl LazySignalsSources<P>,
+        triggers: Vec<Entity>,
+        options: ActionOptions,
+        commands: &mut Commands
+    ) -> ActionHandle {
+        let entity = commands.spawn_empty().id();
+        let function = make_fallible_action_with(task_closure);
+        commands.create_action::<P>(entity, function, sources, triggers);
+        commands.entity(entity).insert(options);
+        ActionHandle::from_entity(entity)
+    }
+
+    /// Append a source to a `Computed`, `Effect`, or `Action` that runs again with it (as the last
+    /// arg) during the next batch. `P` is the args tuple it was created with, so e.g. a source left
+    /// out at creation can be filled in later. Anything else faults it with `InvalidArgs`.
+    pub fn add_source<P: LazySignalsArgs>(
+        &self,
+        derived: impl Into<Entity>,
+        source: impl Into<Entity>,
+        commands: &mut Commands
+    ) {
+        commands.add_source::<P>(derived, source);
     }
 
     /// Create a `BoxedSystem` to be chained after the `Effect` that returns it.
@@ -81,29 +251,146 @@ impl LazySignals {
         Some(Box::new(IntoSystem::into_system(effect_system)))
     }
 
+    /// Cancel every task of an `Action` (or resource) that is running, waiting to be retried, or
+    /// queued. This does not count as a failure.
+    pub fn cancel_action(&self, action: impl Into<Entity>, commands: &mut Commands) {
+        commands.cancel_action(action);
+    }
+
     /// Create a `Computed` that passes its sources to and evaluate a closure, memoizing the result.
     pub fn computed<P: LazySignalsArgs, R: LazySignalsData>(
         &self,
         propagator_closure: impl Computed<P, R>,
-        sources: Vec<Entity>,
+        sources: impl LazySignalsSources<P>,
         commands: &mut Commands
-    ) -> Entity {
+    ) -> Memo<R> {
         let entity = commands.spawn_empty().id();
         commands.create_computed::<P, R>(entity, make_computed_with(propagator_closure), sources);
-        entity
+        Memo::from_entity(entity)
+    }
+
+    /// Create a `Computed` like `computed`, but if any source has an error, the closure does not run
+    /// and the first error is stored as the result instead.
+    pub fn computed_propagating<P: LazySignalsArgs, R: LazySignalsData>(
+        &self,
+        propagator_closure: impl Computed<P, R>,
+        sources: impl LazySignalsSources<P>,
+        commands: &mut Commands
+    ) -> Memo<R> {
+        let entity = commands.spawn_empty().id();
+        commands.create_computed::<P, R>(
+            entity,
+            make_computed_propagating_with(propagator_closure),
+            sources
+        );
+        Memo::from_entity(entity)
+    }
+
+    /// Create a `Computed` that finds its own sources. Whatever the closure reads through the
+    /// `TrackingContext` is subscribed to, and the set is updated each time it runs, so a source
+    /// that is only read some of the time (e.g. behind an `if`) just works.
+    pub fn computed_tracked<R: LazySignalsData>(
+        &self,
+        propagator_closure: impl TrackedComputed<R>,
+        commands: &mut Commands
+    ) -> Memo<R> {
+        let entity = commands.spawn_empty().id();
+        commands.create_computed_tracked::<R>(
+            entity,
+            make_computed_tracked_with(propagator_closure)
+        );
+        Memo::from_entity(entity)
+    }
+
+    /// Create a `Computed` like `computed` but with a custom change detection policy for its result.
+    pub fn computed_with_equality<P: LazySignalsArgs, R: LazySignalsData>(
+        &self,
+        propagator_closure: impl Computed<P, R>,
+        sources: impl LazySignalsSources<P>,
+        equality: LazySignalsEquality<R>,
+        commands: &mut Commands
+    ) -> Memo<R> {
+        let memo = self.computed::<P, R>(propagator_closure, sources, commands);
+        commands.set_equality::<R>(memo, equality);
+        memo
+    }
+
+    /// Create a `Computed` like `computed`, but each arg is a `Result<Option<T>, LazySignalsError>`
+    /// so the closure can tell "no value yet" from "source errored".
+    pub fn computed_with_results<P: LazySignalsArgs, R: LazySignalsData>(
+        &self,
+        propagator_closure: impl Computed<P, R>,
+        sources: impl LazySignalsSources<P>,
+        commands: &mut Commands
+    ) -> Memo<R> {
+        let entity = commands.spawn_empty().id();
+        commands.create_computed_with_results::<P, R>(
+            entity,
+            make_computed_with(propagator_closure),
+            sources
+        );
+        Memo::from_entity(entity)
+    }
+
+    /// Send a message to a reducer to be folded into its state during the next batch. A reducer
+    /// with no data to fold it into (e.g. after `send_error`) drops the message with a warning.
+    pub fn dispatch<M: Send + Sync + 'static>(
+        &self,
+        reducer: impl Into<Entity>,
+        message: M,
+        commands: &mut Commands
+    ) {
+        commands.dispatch::<M>(reducer, message);
     }
 
     /// Create an `Effect` that passes its sources to and evaluate a closure that runs side-effects.
     pub fn effect<P: LazySignalsArgs>(
         &self,
         effect_closure: impl Effect<P>,
-        sources: Vec<Entity>,
+        sources: impl LazySignalsSources<P>,
         triggers: Vec<Entity>,
         commands: &mut Commands
-    ) -> Entity {
+    ) -> EffectHandle {
         let entity = commands.spawn_empty().id();
         commands.create_effect::<P>(entity, make_effect_with(effect_closure), sources, triggers);
-        entity
+        EffectHandle::from_entity(entity)
+    }
+
+    /// Create an `Effect` that runs a system taking `In<P>` instead of a closure. The system is
+    /// registered once, so its state (e.g. `Local`s and change ticks) persists between runs.
+    pub fn effect_system<P: LazySignalsArgs, M>(
+        &self,
+        system: impl IntoSystem<P, (), M>,
+        sources: impl LazySignalsSources<P>,
+        triggers: Vec<Entity>,
+        commands: &mut Commands
+    ) -> EffectHandle {
+        let entity = commands.spawn_empty().id();
+        commands.create_effect_system::<P>(
+            entity,
+            make_effect_system_with(system),
+            sources,
+            triggers
+        );
+        EffectHandle::from_entity(entity)
+    }
+
+    /// Create an `Effect` like `effect`, but each arg is a `Result<Option<T>, LazySignalsError>`.
+    pub fn effect_with_results<P: LazySignalsArgs>(
+        &self,
+        effect_closure: impl Effect<P>,
+        sources: impl LazySignalsSources<P>,
+        triggers: Vec<Entity>,
+        commands: &mut Commands
+    ) -> EffectHandle {
+        let entity = commands.spawn_empty().id();
+        commands.create_effect_with_results::<P>(
+            entity,
+            make_effect_with(effect_closure),
+            sources,
+            triggers
+        );
+        EffectHandle::from_entity(entity)
     }
 
     /// Return an error from a computed closure.
@@ -112,17 +399,27 @@ impl LazySignals {
     }
 
     /// Alias for value.
-    pub fn get<R: LazySignalsData>(&self, immutable: Entity, world: &World) -> Option<R> {
+    pub fn get<R: LazySignalsData>(
+        &self,
+        immutable: impl LazySignalsSource<Option<R>>,
+        world: &World
+    ) -> Option<R> {
         self.value(immutable, world)
     }
 
     /// Check the given entity for an error.
     pub fn get_error<R: LazySignalsData>(
         &self,
-        immutable: Entity,
+        immutable: impl LazySignalsSource<Option<R>>,
         world: &World
     ) -> Option<LazySignalsError> {
-        let entity = world.entity(immutable);
+        let entity = world.entity(immutable.source_entity());
+
+        // a `Computed`, `Effect`, or `Action` that can not run reports why
+        if let Some(faulted) = entity.get::<Faulted>() {
+            return Some(faulted.error.clone());
+        }
+
         match entity.get::<LazySignalsState<R>>() {
             Some(observable) => observable.error(),
             None => None,
@@ -135,48 +432,222 @@ impl LazySignals {
     }
 
     /// Alias for value.
-    pub fn read<R: LazySignalsData>(&self, immutable: Entity, world: &World) -> Option<R> {
+    pub fn read<R: LazySignalsData>(
+        &self,
+        immutable: impl LazySignalsSource<Option<R>>,
+        world: &World
+    ) -> Option<R> {
         self.value(immutable, world)
     }
 
+    /// Get every value delivered by a stream during the current tick from the given `World`. These
+    /// are the values sent before the batch ran, so they stay readable for the rest of the frame.
+    pub fn read_stream<T: LazySignalsData>(
+        &self,
+        stream: impl LazySignalsSource<Vec<T>>,
+        world: &World
+    ) -> Vec<T> {
+        let entity = world.entity(stream.source_entity());
+        match entity.get::<LazySignalsStream<T>>() {
+            Some(stream) => stream.values(),
+            None => Vec::<T>::new(),
+        }
+    }
+
+    /// Create a `Reducer`, a state that is only changed by dispatching messages of type `M`.
+    pub fn reducer<S: LazySignalsData, M: Send + Sync + 'static>(
+        &self,
+        data: S,
+        reducer_closure: impl Reducer<S, M>,
+        commands: &mut Commands
+    ) -> Signal<S> {
+        let entity = commands.spawn_empty().id();
+        commands.create_reducer::<S, M>(entity, data, make_reducer_with(reducer_closure));
+        Signal::from_entity(entity)
+    }
+
+    /// Remove a source from a `Computed`, `Effect`, or `Action` that runs again without it during
+    /// the next batch. `P` is the args tuple it was created with, or it is faulted with
+    /// `InvalidArgs`.
+    pub fn remove_source<P: LazySignalsArgs>(
+        &self,
+        derived: impl Into<Entity>,
+        source: impl Into<Entity>,
+        commands: &mut Commands
+    ) {
+        commands.remove_source::<P>(derived, source);
+    }
+
+    /// Create a `Signal` that holds the `AsyncStatus` of an async closure, which runs as the task of
+    /// an `Action` whenever its sources change. The status is `Pending` while a task runs, then
+    /// `Ready` or `Failed` once it completes. A newer run cancels a task that is still running.
+    pub fn resource<P: LazySignalsArgs, R: LazySignalsData>(
+        &self,
+        async_closure: impl AsyncResource<P, R>,
+        sources: impl LazySignalsSources<P>,
+        commands: &mut Commands
+    ) -> Signal<AsyncStatus<R>> {
+        let resource = commands.spawn_empty().id();
+        commands.create_state::<AsyncStatus<R>>(resource, AsyncStatus::Idle);
+
+        // the action lives on the same entity, so it goes away with the status
+        let task_closure = move |args: P| {
+            let future = async_closure.fetch(args);
+            AsyncComputeTaskPool::get().spawn(async move {
+                // an error is sent as `Failed` by the framework, after any retries
+                let data = AsyncStatus::Ready(future.await?);
+                let mut command_queue = CommandQueue::default();
+                command_queue.push(SendSignalCommand { signal: resource, data });
+                Ok(command_queue)
+            })
+        };
+        let function = make_fallible_action_with(task_closure);
+        commands.create_action::<P>(resource, function, sources, vec![]);
+        commands
+            .entity(resource)
+            .insert((
+                ActionOptions { concurrency: ActionConcurrency::Switch, ..default() },
+                LazyResource::new::<R>(),
+            ));
+
+        Signal::from_entity(resource)
+    }
+
     /// Return a value from a computed closure.
     pub fn result<T: LazySignalsData>(data: T) -> LazySignalsResult<T> {
         LazySignalsResult { data: Some(data), error: None }
     }
 
     /// Send a signal to be applied during the next batch.
-    pub fn send<T: LazySignalsData>(&self, signal: Entity, data: T, commands: &mut Commands) {
+    pub fn send<T: LazySignalsData>(
+        &self,
+        signal: impl LazySignalsTarget<T>,
+        data: T,
+        commands: &mut Commands
+    ) {
         commands.send_signal::<T>(signal, data);
     }
 
+    /// Send an error to be applied to a signal during the next batch.
+    pub fn send_error<T: LazySignalsData>(
+        &self,
+        signal: impl LazySignalsTarget<T>,
+        error: LazySignalsError,
+        commands: &mut Commands
+    ) {
+        commands.send_error::<T>(signal, error);
+    }
+
     /// Send a signal to be applied during the next batch regardless of whether the data changed.
     pub fn send_and_trigger<T: LazySignalsData>(
         &self,
-        signal: Entity,
+        signal: impl LazySignalsTarget<T>,
         data: T,
         commands: &mut Commands
     ) {
         commands.trigger_signal::<T>(signal, data);
     }
 
+    /// Append a value to a stream to be delivered, along with every other value sent this tick,
+    /// during the next batch.
+    pub fn send_to_stream<T: LazySignalsData>(
+        &self,
+        stream: impl LazySignalsSource<Vec<T>>,
+        data: T,
+        commands: &mut Commands
+    ) {
+        commands.send_to_stream::<T>(stream, data);
+    }
+
+    /// Replace the sources of a `Computed`, `Effect`, or `Action`. It unsubscribes from the old
+    /// sources and runs again with the new ones during the next batch. `P` is the args tuple it was
+    /// created with, or it is faulted with `InvalidArgs`.
+    pub fn set_sources<P: LazySignalsArgs>(
+        &self,
+        derived: impl Into<Entity>,
+        sources: impl LazySignalsSources<P>,
+        commands: &mut Commands
+    ) {
+        commands.set_sources::<P>(derived, sources);
+    }
+
+    /// Create a `Signal` state that keeps its data behind an `Arc`, so every read shares it.
+    pub fn shared<T: Send + Sync + TypePath + 'static>(
+        &self,
+        data: T,
+        commands: &mut Commands
+    ) -> Signal<Shared<T>>
+        where Shared<T>: LazySignalsData
+    {
+        commands.register_clone::<Shared<T>>();
+        self.state(Shared::new(data), commands)
+    }
+
     /// Create a `Signal` state that is the entrypoint for data into the structure.
-    pub fn state<T: LazySignalsData>(&self, data: T, commands: &mut Commands) -> Entity {
+    pub fn state<T: LazySignalsData>(&self, data: T, commands: &mut Commands) -> Signal<T> {
         let state = commands.spawn_empty().id();
         commands.create_state::<T>(state, data);
+        Signal::from_entity(state)
+    }
+
+    /// Create a `Signal` state like `state` but with a custom change detection policy.
+    pub fn state_with_equality<T: LazySignalsData>(
+        &self,
+        data: T,
+        equality: LazySignalsEquality<T>,
+        commands: &mut Commands
+    ) -> Signal<T> {
+        let state = self.state::<T>(data, commands);
+        commands.set_equality::<T>(state, equality);
         state
     }
 
+    /// Create a `Signal` stream that delivers every value sent in a tick as a `Vec<T>`.
+    pub fn stream<T: LazySignalsData>(&self, commands: &mut Commands) -> StreamHandle<T> {
+        let stream = commands.spawn_empty().id();
+        commands.create_stream::<T>(stream);
+        StreamHandle::from_entity(stream)
+    }
+
     /// Trigger a Signal that takes the unit type as its generic param..
-    pub fn trigger(&self, signal: Entity, commands: &mut Commands) {
+    pub fn trigger(&self, signal: impl LazySignalsTarget<()>, commands: &mut Commands) {
         commands.trigger_signal::<()>(signal, ());
     }
 
+    /// Send a signal whose value is derived from the current one during the next batch.
+    /// Every update sent in the same tick is applied in order.
+    pub fn update<T: LazySignalsData>(
+        &self,
+        signal: impl LazySignalsTarget<T>,
+        update: impl Updater<T>,
+        commands: &mut Commands
+    ) {
+        commands.update_signal::<T>(signal, update);
+    }
+
     /// Get the value from the given `World`.
-    pub fn value<R: LazySignalsData>(&self, immutable: Entity, world: &World) -> Option<R> {
-        let entity = world.entity(immutable);
+    pub fn value<R: LazySignalsData>(
+        &self,
+        immutable: impl LazySignalsSource<Option<R>>,
+        world: &World
+    ) -> Option<R> {
+        let entity = world.entity(immutable.source_entity());
         match entity.get::<LazySignalsState<R>>() {
             Some(observable) => observable.get(),
             None => None,
         }
     }
+
+    /// Get an async `Stream` of the later values of a `Signal` or `Computed`, e.g. so an `Action`
+    /// task can wait for a change with `wait_for` or `changed`. It starts receiving values once
+    /// the command is applied.
+    pub fn watch<T: LazySignalsData>(
+        &self,
+        immutable: impl LazySignalsSource<Option<T>>,
+        commands: &mut Commands
+    ) -> SignalStream<T> {
+        let (watcher, stream) = SignalStream::channel();
+        commands.watch_signal::<T>(immutable, watcher);
+        stream
+    }
 }
@@ -1,19 +1,21 @@
-use std::{ any::TypeId, sync::RwLockReadGuard };
+use std::any::TypeId;
 
 use bevy::{
     ecs::{
         change_detection::MutUntyped,
         component::ComponentId,
         entity::Entity,
+        storage::SparseSet,
         world::EntityWorldMut,
     },
     prelude::*,
-    reflect::{ DynamicTuple, ReflectFromPtr, TypeRegistry },
+    reflect::{ DynamicTuple, TypeInfo },
 };
 
 use crate::{
     framework::*,
-    lazy_immutable::{ LazySignalsObservable, ReflectLazySignalsObservable },
+    graph::{ find_cycle, find_height },
+    lazy_immutable::LazySignalsObservable,
 };
 
 /// Convenience fn to clone the un-`Clone`-able.
@@ -24,61 +26,130 @@ pub fn clone_data<T: LazySignalsData>(result: &LazySignalsResult<T>) -> LazySign
     };
     LazySignalsResult {
         data,
-        error: result.error,
+        error: result.error.clone(),
     }
 }
 
-/// Convenience fn to add a concrete value to a dynamic tuple proxy.
-pub fn insert_data<T: LazySignalsData>(args: &mut DynamicTuple, result: &LazySignalsResult<T>) {
+/// Convenience fn to clone a list of the un-`Clone`-able.
+pub fn clone_items<T: LazySignalsData>(items: &[T]) -> Vec<T> {
+    items
+        .iter()
+        .filter_map(|item| <T as FromReflect>::from_reflect(&*item.clone_value()))
+        .collect()
+}
+
+/// Convenience fn to add a concrete value (already copied by the caller) to a dynamic tuple proxy.
+pub fn insert_data<T: LazySignalsData>(args: &mut DynamicTuple, result: LazySignalsResult<T>) {
     // the type inserted here has to be `Option<T>`
 
     // let's look at the error and return `None` if it is `Some`, otherwise just return `Some(data)`
-    let cloned = clone_data::<T>(result);
-    let result = match cloned.error {
-        Some(_) => None,
-        None => cloned.data,
+    let result = match result.error {
+        Some(error) => {
+            trace!("-passing None for source with error: {}", error);
+            None
+        }
+        None => result.data,
     };
     args.insert(result);
 }
 
-/// Convenience fn to convert a `DynamicTuple` into a concrete type.
-pub fn make_tuple<T: LazySignalsArgs>(tuple: &DynamicTuple) -> T {
-    <T as FromReflect>::from_reflect(tuple).unwrap()
+/// Convenience fn to convert a `DynamicTuple` into a concrete type by moving the values out of it.
+pub fn make_tuple<T: LazySignalsArgs>(tuple: DynamicTuple) -> Result<T, LazySignalsError> {
+    T::take_args(tuple).ok_or_else(invalid_tuple::<T>)
+}
+
+/// Convenience fn to convert a `DynamicTuple` into a concrete type by copying the values, for args
+/// that have to be kept (e.g. to retry an `Action`).
+pub fn copy_tuple<T: LazySignalsArgs>(tuple: &DynamicTuple) -> Result<T, LazySignalsError> {
+    <T as FromReflect>::from_reflect(tuple).ok_or_else(invalid_tuple::<T>)
+}
+
+fn invalid_tuple<T: LazySignalsArgs>() -> LazySignalsError {
+    LazySignalsError::InvalidArgs(
+        format!("the args do not fit {}", std::any::type_name::<T>())
+    )
+}
+
+/// Check that the `sources` line up with the args tuple `P`, using the registered `TypeInfo` of `P`
+/// and the `ImmutableState` of each source. Better to find out now than to panic in `make_tuple`.
+pub fn validate_args<P: LazySignalsArgs>(
+    sources: &[Entity],
+    args_mode: ArgsMode,
+    world: &World
+) -> Result<(), LazySignalsError> {
+    let type_info = world
+        .get_resource::<AppTypeRegistry>()
+        .and_then(|type_registry| type_registry.read().get_type_info(TypeId::of::<P>()))
+        .unwrap_or_else(|| P::get_type_registration().type_info());
+
+    let TypeInfo::Tuple(tuple_info) = type_info else {
+        return Err(
+            LazySignalsError::InvalidArgs(format!("{} is not a tuple", type_info.type_path()))
+        );
+    };
+
+    // arity
+    if tuple_info.field_len() != sources.len() {
+        return Err(
+            LazySignalsError::InvalidArgs(
+                format!(
+                    "{} expects {} sources but got {}",
+                    tuple_info.type_path(),
+                    tuple_info.field_len(),
+                    sources.len()
+                )
+            )
+        );
+    }
+
+    // element types
+    for (index, source) in sources.iter().enumerate() {
+        let field = tuple_info.field_at(index).unwrap();
+        let Some(immutable) = world
+            .get_entity(*source)
+            .and_then(|source| source.get::<ImmutableState>()) else {
+            return Err(
+                LazySignalsError::InvalidArgs(
+                    format!("source {} ({}) is not a signal or computed", index, source)
+                )
+            );
+        };
+
+        let arg_type = immutable.arg_type(args_mode);
+        if arg_type.type_id != field.type_id() {
+            return Err(
+                LazySignalsError::InvalidArgs(
+                    format!(
+                        "source {} ({}) provides {} but {} expects {}",
+                        index,
+                        source,
+                        arg_type.type_name,
+                        tuple_info.type_path(),
+                        field.type_path()
+                    )
+                )
+            );
+        }
+    }
+
+    Ok(())
 }
 
 /// Given mutable reference to a `LazySignalsState` component instance, make a `LazySignalsObservable`.
 pub fn ph_nglui_mglw_nafh_cthulhu_r_lyeh_wgah_nagl_fhtagn<'a>(
     mut_untyped: &'a mut MutUntyped,
-    type_id: &TypeId,
-    type_registry: &RwLockReadGuard<TypeRegistry>
+    vtable: &LazySignalsVTable
 ) -> &'a mut dyn LazySignalsObservable {
     // convert into a pointer
     let ptr_mut = mut_untyped.as_mut();
 
-    // the `type_registration` is used to build a strategy to dereference a pointer to the component
-
-    // the `TypeId` refers to the `LazySignalsState<T>` component with concrete `T`
-    let type_registration = type_registry.get(*type_id).unwrap();
-
-    // since we're reflecting from a pointer, we're gonna need this
-    let reflect_from_ptr = type_registration.data::<ReflectFromPtr>().unwrap().clone();
-
-    // I think we're sorta getting a proxy to the vtable for the concrete type and then schlepping
-    // it into the reflected proxy for the pointer to the concrete component (value)
+    // the vtable was made by the create command for the concrete `LazySignalsState<T>` (or stream)
+    // that was stored under this `ComponentId`, so no need to ask the `TypeRegistry` every time
 
-    // since we know the `TypeId` of the actual component, we can then downcast it into a
-    // non-reflected trait object backed by the reflected proxy
+    // the sun grew dark and cold, and the seas boiled
 
-    // safety: `value` implements reflected trait `LazySignalsObservable`, what for `ReflectFromPtr`
-    let value = unsafe { reflect_from_ptr.as_reflect_mut(ptr_mut) };
-
-    // the sun grew dark and cold
-    let reflect_observable = type_registry
-        .get_type_data::<ReflectLazySignalsObservable>(value.type_id())
-        .unwrap();
-
-    // the seas boiled
-    reflect_observable.get_mut(value).unwrap()
+    // safety: the `ComponentId` of the pointer is the one the vtable was registered for
+    unsafe { (vtable.as_observable)(ptr_mut) }
 }
 
 /// Make a `LazySignalsObservable` out of `EntityWorldMut`, passing optional `args` and target `Entity`.
@@ -88,18 +159,18 @@ pub fn run_as_observable(
     args: Option<&mut DynamicTuple>,
     target: Option<&Entity>,
     component_id: &ComponentId,
-    type_id: &TypeId,
-    type_registry: &RwLockReadGuard<TypeRegistry>,
-    mut closure: Box<dyn ObservableFn>
+    registry: &LazySignalsRegistry,
+    mut closure: Box<dyn ObservableFn + '_>
 ) -> MaybeFlaggedEntities {
+    let Some(vtable) = registry.get(*component_id) else {
+        error!("no LazySignalsVTable registered for {:?}", component_id);
+        return None;
+    };
+
     // get the source `LazySignalsState` component as an ECS change detection handle
     if let Some(mut mut_untyped) = entity.get_mut_by_id(*component_id) {
         // ...and convert that into a trait object
-        let observable = ph_nglui_mglw_nafh_cthulhu_r_lyeh_wgah_nagl_fhtagn(
-            &mut mut_untyped,
-            type_id,
-            type_registry
-        );
+        let observable = ph_nglui_mglw_nafh_cthulhu_r_lyeh_wgah_nagl_fhtagn(&mut mut_untyped, vtable);
 
         // run the supplied fn
         closure(Box::new(observable), args, target)
@@ -108,16 +179,114 @@ pub fn run_as_observable(
     }
 }
 
+/// Check that an entity still exists and still has something to observe.
+pub fn is_observable(entity: &Entity, world: &World) -> bool {
+    world.get_entity(*entity).is_some_and(|entity| entity.contains::<ImmutableState>())
+}
+
+/// Mark a `Computed`, `Effect`, or `Action` that can not run anymore. A `Computed` also sends the
+/// error to its own state so anything downstream finds out about it.
+pub fn fault_derived(
+    entity: &Entity,
+    error: LazySignalsError,
+    registry: &LazySignalsRegistry,
+    world: &mut World
+) {
+    trace!("Faulting {:?}: {}", entity, error);
+    if let Some(mut derived) = world.get_entity_mut(*entity) {
+        let memo = derived
+            .contains::<ComputedImmutable>()
+            .then(|| derived.get::<ImmutableState>().map(|immutable| immutable.component_id))
+            .flatten();
+
+        if let Some(component_id) = memo {
+            let error = error.clone();
+            run_as_observable(
+                &mut derived,
+                None,
+                None,
+                &component_id,
+                registry,
+                Box::new(move |observable, _args, _target| {
+                    observable.merge_error(error.clone());
+                    None
+                })
+            );
+            derived.insert(SendSignal);
+        }
+
+        derived.remove::<(ComputeMemo, DeferredEffect, Dirty)>();
+        derived.insert(Faulted { error });
+    }
+}
+
+/// Swap the sources of a tracked `Computed` for whatever it read on its last run. New sources are
+/// subscribed right away, old ones are dropped, and the `Rank` follows the new sources.
+pub fn track_sources(entity: &Entity, reads: Vec<Entity>, world: &mut World) {
+    let Some(computed) = world.get::<ComputedImmutable>(*entity) else {
+        return;
+    };
+    if computed.sources == reads {
+        return;
+    }
+    trace!("Tracking {:?} with sources {:?}", entity, reads);
+    let old_sources = computed.sources.clone();
+
+    world.resource_scope(|world, registry: Mut<LazySignalsRegistry>| {
+        // a computed that reads itself (even indirectly) would never finish computing
+        if let Some(path) = find_cycle(*entity, &reads, world) {
+            let error = LazySignalsError::Cycle(path);
+            error!("{:?} will not run: {}", entity, error);
+            fault_derived(entity, error, &registry, world);
+            return;
+        }
+
+        for source in old_sources.iter().filter(|source| !reads.contains(source)) {
+            unsubscribe(entity, source, &registry, world);
+        }
+        for source in reads.iter() {
+            subscribe(entity, source, &registry, world);
+        }
+
+        let mut heights = SparseSet::<Entity, u32>::new();
+        let height = reads
+            .iter()
+            .map(|source| find_height(*source, world, &mut heights) + 1)
+            .max()
+            .unwrap_or(0);
+
+        let mut handle = world.entity_mut(*entity);
+        handle.get_mut::<ComputedImmutable>().unwrap().sources = reads;
+        handle.insert(Rank { height });
+    });
+}
+
+/// Log and send an event when a `LazySignalsConfig` limit is hit.
+pub fn report_loop(
+    limit: LazySignalsLimit,
+    chain: Vec<Entity>,
+    cut_off: bool,
+    world: &mut World
+) {
+    let path = format_path(&chain);
+    if cut_off {
+        error!("{:?} limit hit, cutting off loop: {}", limit, path);
+    } else {
+        warn!("{:?} limit hit, possible loop: {}", limit, path);
+    }
+
+    world.send_event(LazySignalsLoopDetected { limit, chain, cut_off });
+}
+
 /// Convenience fn to subscribe an entity to a source.
 pub fn subscribe(
     entity: &Entity,
     source: &Entity,
-    type_registry: &RwLockReadGuard<TypeRegistry>,
+    registry: &LazySignalsRegistry,
     world: &mut World
 ) {
-    // get the `TypeId` of each source (`Signal` or `Computed`) component
+    // get the `ComponentId` of each source (`Signal` or `Computed`) component
     let mut component_id: Option<ComponentId> = None;
-    let mut type_id: Option<TypeId> = None;
 
     trace!("Subscribing {:#?} to {:?}", entity, source);
 
@@ -129,26 +298,18 @@ pub fn subscribe(
             trace!("-got ImmutableState");
             // ...as a `LazySignalsObservable`
             component_id = Some(immutable_state.component_id);
-            if let Some(info) = world.components().get_info(component_id.unwrap()) {
-                trace!("-got TypeId");
-                type_id = info.type_id();
-            }
         }
     }
 
-    // we have a component and a type, now do `mut` stuff
-    if component_id.is_some() && type_id.is_some() {
+    // we have a component, now do `mut` stuff
+    if let Some(component_id) = component_id {
         if let Some(mut source) = world.get_entity_mut(*source) {
-            let component_id = &component_id.unwrap();
-            let type_id = type_id.unwrap();
-
             run_as_observable(
                 &mut source,
                 None,
                 Some(entity),
-                component_id,
-                &type_id,
-                type_registry,
+                &component_id,
+                registry,
                 Box::new(|observable, _args, target| {
                     observable.subscribe(*target.unwrap());
                     observable.merge_subscribers();
@@ -158,3 +319,35 @@ pub fn subscribe(
         }
     }
 }
+
+/// Convenience fn to unsubscribe an entity from a source.
+pub fn unsubscribe(
+    entity: &Entity,
+    source: &Entity,
+    registry: &LazySignalsRegistry,
+    world: &mut World
+) {
+    trace!("Unsubscribing {:#?} from {:?}", entity, source);
+
+    // a source that was already despawned has nothing to unsubscribe from
+    let Some(component_id) = world
+        .get_entity(*source)
+        .and_then(|source| source.get::<ImmutableState>())
+        .map(|immutable_state| immutable_state.component_id) else {
+        return;
+    };
+
+    if let Some(mut source) = world.get_entity_mut(*source) {
+        run_as_observable(
+            &mut source,
+            None,
+            Some(entity),
+            &component_id,
+            registry,
+            Box::new(|observable, _args, target| {
+                observable.unsubscribe(*target.unwrap());
+                None
+            })
+        );
+    }
+}
@@ -1,17 +1,40 @@
-use std::{ marker::PhantomData, sync::Mutex };
+use std::{ any::{ type_name, TypeId }, marker::PhantomData, sync::{ Arc, Mutex } };
 
-use bevy::{ ecs::world::Command, prelude::* };
+use bevy::{
+    ecs::{ component::ComponentId, system::BoxedSystem, world::Command },
+    prelude::*,
+    reflect::DynamicTuple,
+};
 
-use crate::{ bundles::*, framework::*, lazy_immutable::{ LazySignalsState, LazySignalsImmutable } };
+use crate::{
+    arcane_wizardry::{ fault_derived, unsubscribe, validate_args },
+    bundles::*,
+    framework::*,
+    graph::find_cycle,
+    handles::*,
+    lazy_immutable::{ LazySignalsImmutable, LazySignalsObservable, LazySignalsState },
+    lazy_stream::LazySignalsStream,
+    signal_stream::SignalWatcher,
+};
 
 /// Convenience extension to use each `Command` directly from `Commands` instance.
 pub trait LazySignalsCommandsExt {
+    /// Command to append a source to a computed memo or effect created with args `P`.
+    fn add_source<P: LazySignalsArgs>(
+        &mut self,
+        derived: impl Into<Entity>,
+        source: impl Into<Entity>
+    );
+
+    /// Command to cancel every task of an action, including any retries or queued runs.
+    fn cancel_action(&mut self, action: impl Into<Entity>);
+
     /// Command to create an action (effect) from the given entity as an async task.
     fn create_action<P: LazySignalsArgs>(
         &mut self,
         effect: Entity,
         function: Mutex<Box<dyn ActionWrapper>>,
-        sources: Vec<Entity>,
+        sources: impl LazySignalsSources<P>,
         triggers: Vec<Entity>
     );
 
@@ -20,7 +43,22 @@ pub trait LazySignalsCommandsExt {
         &mut self,
         computed: Entity,
         function: Mutex<Box<dyn ComputedContext>>,
-        sources: Vec<Entity>
+        sources: impl LazySignalsSources<P>
+    );
+
+    /// Command to create a computed memo whose sources are whatever its closure read last time.
+    fn create_computed_tracked<R: LazySignalsData>(
+        &mut self,
+        computed: Entity,
+        function: Mutex<Box<dyn ComputedContext>>
+    );
+
+    /// Command to create a computed memo whose args tuple holds a `Result` for each source.
+    fn create_computed_with_results<P: LazySignalsArgs, R: LazySignalsData>(
+        &mut self,
+        computed: Entity,
+        function: Mutex<Box<dyn ComputedContext>>,
+        sources: impl LazySignalsSources<P>
     );
 
     /// Command to create a short-lived effect from the given entity.
@@ -28,33 +66,134 @@ pub trait LazySignalsCommandsExt {
         &mut self,
         effect: Entity,
         function: Mutex<Box<dyn EffectWrapper>>,
-        sources: Vec<Entity>,
+        sources: impl LazySignalsSources<P>,
         triggers: Vec<Entity>
     );
 
+    /// Command to create an effect that runs a one-shot system, registered when the command runs.
+    fn create_effect_system<P: LazySignalsArgs>(
+        &mut self,
+        effect: Entity,
+        system: BoxedSystem<DynamicTuple, ()>,
+        sources: impl LazySignalsSources<P>,
+        triggers: Vec<Entity>
+    );
+
+    /// Command to create a short-lived effect whose args tuple holds a `Result` for each source.
+    fn create_effect_with_results<P: LazySignalsArgs>(
+        &mut self,
+        effect: Entity,
+        function: Mutex<Box<dyn EffectWrapper>>,
+        sources: impl LazySignalsSources<P>,
+        triggers: Vec<Entity>
+    );
+
+    /// Command to create a reducer (state that folds dispatched messages) from the given entity.
+    fn create_reducer<S: LazySignalsData, M: Send + Sync + 'static>(
+        &mut self,
+        reducer: Entity,
+        data: S,
+        function: Arc<dyn ReducerWrapper<M>>
+    );
+
     /// Command to create a state (`LazyImmutable` with no `Effect` or `Computed`) from the given entity.
     fn create_state<T: LazySignalsData>(&mut self, state: Entity, data: T);
 
+    /// Command to create a stream (non-lossy `LazyImmutable` that collects every value sent in a tick).
+    fn create_stream<T: LazySignalsData>(&mut self, stream: Entity);
+
+    /// Command to dispatch a message to a reducer during the next batch.
+    fn dispatch<M: Send + Sync + 'static>(&mut self, reducer: impl Into<Entity>, message: M);
+
+    /// Command to let states of a `Clone` type created after this copy values without reflection.
+    fn register_clone<T: LazySignalsData + Clone>(&mut self);
+
+    /// Command to send an error to a signal, to be applied during the next batch.
+    fn send_error<T: LazySignalsData>(
+        &mut self,
+        signal: impl LazySignalsTarget<T>,
+        error: LazySignalsError
+    );
+
+    /// Command to remove a source from a computed memo or effect created with args `P`.
+    fn remove_source<P: LazySignalsArgs>(
+        &mut self,
+        derived: impl Into<Entity>,
+        source: impl Into<Entity>
+    );
+
     // Command to send a signal if the data value is different from the current value.
-    fn send_signal<T: LazySignalsData>(&mut self, signal: Entity, data: T);
+    fn send_signal<T: LazySignalsData>(&mut self, signal: impl LazySignalsTarget<T>, data: T);
+
+    /// Command to append a value to a stream to be delivered during the next batch.
+    fn send_to_stream<T: LazySignalsData>(
+        &mut self,
+        stream: impl LazySignalsSource<Vec<T>>,
+        data: T
+    );
+
+    /// Command to replace the change detection policy of a state or computed memo.
+    fn set_equality<T: LazySignalsData>(
+        &mut self,
+        immutable: impl LazySignalsSource<Option<T>>,
+        equality: LazySignalsEquality<T>
+    );
+
+    /// Command to replace the sources of a computed memo or effect created with args `P`.
+    fn set_sources<P: LazySignalsArgs>(
+        &mut self,
+        derived: impl Into<Entity>,
+        sources: impl LazySignalsSources<P>
+    );
 
     // Command to send a signal even if the data value is unchanged.
-    fn trigger_signal<T: LazySignalsData>(&mut self, signal: Entity, data: T);
+    fn trigger_signal<T: LazySignalsData>(&mut self, signal: impl LazySignalsTarget<T>, data: T);
+
+    /// Command to send a signal whose value is derived from the current one when it is applied.
+    fn update_signal<T: LazySignalsData>(
+        &mut self,
+        signal: impl LazySignalsTarget<T>,
+        update: impl Updater<T>
+    );
+
+    /// Command to feed each later change of a signal or computed to a `SignalStream`.
+    fn watch_signal<T: LazySignalsData>(
+        &mut self,
+        immutable: impl LazySignalsSource<Option<T>>,
+        watcher: SignalWatcher<T>
+    );
 }
 
 impl<'w, 's> LazySignalsCommandsExt for Commands<'w, 's> {
+    fn add_source<P: LazySignalsArgs>(
+        &mut self,
+        derived: impl Into<Entity>,
+        source: impl Into<Entity>
+    ) {
+        self.add(AddSourceCommand::<P> {
+            derived: derived.into(),
+            source: source.into(),
+            args_type: PhantomData,
+        });
+    }
+
+    fn cancel_action(&mut self, action: impl Into<Entity>) {
+        self.add(CancelActionCommand { action: action.into() });
+    }
+
     fn create_action<P: LazySignalsArgs>(
         &mut self,
         effect: Entity,
         function: Mutex<Box<dyn ActionWrapper>>,
-        sources: Vec<Entity>,
+        sources: impl LazySignalsSources<P>,
         triggers: Vec<Entity>
     ) {
         self.add(CreateActionCommand::<P> {
             effect,
             function,
-            sources,
+            sources: sources.into_entities(),
             triggers,
+            args_mode: ArgsMode::Options,
             args_type: PhantomData,
         });
     }
@@ -63,12 +202,41 @@ impl<'w, 's> LazySignalsCommandsExt for Commands<'w, 's> {
         &mut self,
         computed: Entity,
         function: Mutex<Box<dyn ComputedContext>>,
-        sources: Vec<Entity>
+        sources: impl LazySignalsSources<P>
+    ) {
+        self.add(CreateComputedCommand::<P, R> {
+            computed,
+            function,
+            sources: sources.into_entities(),
+            args_mode: ArgsMode::Options,
+            args_type: PhantomData,
+            result_type: PhantomData,
+        });
+    }
+
+    fn create_computed_tracked<R: LazySignalsData>(
+        &mut self,
+        computed: Entity,
+        function: Mutex<Box<dyn ComputedContext>>
+    ) {
+        self.add(CreateComputedTrackedCommand::<R> {
+            computed,
+            function,
+            result_type: PhantomData,
+        });
+    }
+
+    fn create_computed_with_results<P: LazySignalsArgs, R: LazySignalsData>(
+        &mut self,
+        computed: Entity,
+        function: Mutex<Box<dyn ComputedContext>>,
+        sources: impl LazySignalsSources<P>
     ) {
         self.add(CreateComputedCommand::<P, R> {
             computed,
             function,
-            sources,
+            sources: sources.into_entities(),
+            args_mode: ArgsMode::Results,
             args_type: PhantomData,
             result_type: PhantomData,
         });
@@ -78,18 +246,66 @@ impl<'w, 's> LazySignalsCommandsExt for Commands<'w, 's> {
         &mut self,
         effect: Entity,
         function: Mutex<Box<dyn EffectWrapper>>,
-        sources: Vec<Entity>,
+        sources: impl LazySignalsSources<P>,
+        triggers: Vec<Entity>
+    ) {
+        self.add(CreateEffectCommand::<P> {
+            effect,
+            function,
+            sources: sources.into_entities(),
+            triggers,
+            args_mode: ArgsMode::Options,
+            args_type: PhantomData,
+        });
+    }
+
+    fn create_effect_system<P: LazySignalsArgs>(
+        &mut self,
+        effect: Entity,
+        system: BoxedSystem<DynamicTuple, ()>,
+        sources: impl LazySignalsSources<P>,
+        triggers: Vec<Entity>
+    ) {
+        self.add(CreateEffectSystemCommand::<P> {
+            effect,
+            system,
+            sources: sources.into_entities(),
+            triggers,
+            args_mode: ArgsMode::Options,
+            args_type: PhantomData,
+        });
+    }
+
+    fn create_effect_with_results<P: LazySignalsArgs>(
+        &mut self,
+        effect: Entity,
+        function: Mutex<Box<dyn EffectWrapper>>,
+        sources: impl LazySignalsSources<P>,
         triggers: Vec<Entity>
     ) {
         self.add(CreateEffectCommand::<P> {
             effect,
             function,
-            sources,
+            sources: sources.into_entities(),
             triggers,
+            args_mode: ArgsMode::Results,
             args_type: PhantomData,
         });
     }
 
+    fn create_reducer<S: LazySignalsData, M: Send + Sync + 'static>(
+        &mut self,
+        reducer: Entity,
+        data: S,
+        function: Arc<dyn ReducerWrapper<M>>
+    ) {
+        self.add(CreateReducerCommand::<S, M> {
+            reducer,
+            data,
+            function,
+        });
+    }
+
     fn create_state<T: LazySignalsData>(&mut self, state: Entity, data: T) {
         self.add(CreateStateCommand {
             state,
@@ -97,19 +313,282 @@ impl<'w, 's> LazySignalsCommandsExt for Commands<'w, 's> {
         });
     }
 
-    fn send_signal<T: LazySignalsData>(&mut self, signal: Entity, data: T) {
+    fn create_stream<T: LazySignalsData>(&mut self, stream: Entity) {
+        self.add(CreateStreamCommand::<T> {
+            stream,
+            data_type: PhantomData,
+        });
+    }
+
+    fn dispatch<M: Send + Sync + 'static>(&mut self, reducer: impl Into<Entity>, message: M) {
+        self.add(DispatchCommand {
+            reducer: reducer.into(),
+            message,
+        });
+    }
+
+    fn register_clone<T: LazySignalsData + Clone>(&mut self) {
+        self.add(RegisterCloneCommand::<T> {
+            data_type: PhantomData,
+        });
+    }
+
+    fn send_error<T: LazySignalsData>(
+        &mut self,
+        signal: impl LazySignalsTarget<T>,
+        error: LazySignalsError
+    ) {
+        self.add(SendErrorCommand::<T> {
+            signal: signal.target_entity(),
+            error,
+            data_type: PhantomData,
+        });
+    }
+
+    fn remove_source<P: LazySignalsArgs>(
+        &mut self,
+        derived: impl Into<Entity>,
+        source: impl Into<Entity>
+    ) {
+        self.add(RemoveSourceCommand::<P> {
+            derived: derived.into(),
+            source: source.into(),
+            args_type: PhantomData,
+        });
+    }
+
+    fn send_signal<T: LazySignalsData>(&mut self, signal: impl LazySignalsTarget<T>, data: T) {
         self.add(SendSignalCommand {
-            signal,
+            signal: signal.target_entity(),
             data,
         });
     }
 
-    fn trigger_signal<T: LazySignalsData>(&mut self, signal: Entity, data: T) {
+    fn send_to_stream<T: LazySignalsData>(
+        &mut self,
+        stream: impl LazySignalsSource<Vec<T>>,
+        data: T
+    ) {
+        self.add(SendToStreamCommand {
+            stream: stream.source_entity(),
+            data,
+        });
+    }
+
+    fn set_equality<T: LazySignalsData>(
+        &mut self,
+        immutable: impl LazySignalsSource<Option<T>>,
+        equality: LazySignalsEquality<T>
+    ) {
+        self.add(SetEqualityCommand {
+            immutable: immutable.source_entity(),
+            equality,
+        });
+    }
+
+    fn set_sources<P: LazySignalsArgs>(
+        &mut self,
+        derived: impl Into<Entity>,
+        sources: impl LazySignalsSources<P>
+    ) {
+        self.add(SetSourcesCommand::<P> {
+            derived: derived.into(),
+            sources: sources.into_entities(),
+            args_type: PhantomData,
+        });
+    }
+
+    fn trigger_signal<T: LazySignalsData>(&mut self, signal: impl LazySignalsTarget<T>, data: T) {
         self.add(TriggerSignalCommand {
-            signal,
+            signal: signal.target_entity(),
             data,
         });
     }
+
+    fn update_signal<T: LazySignalsData>(
+        &mut self,
+        signal: impl LazySignalsTarget<T>,
+        update: impl Updater<T>
+    ) {
+        self.add(UpdateSignalCommand::<T> {
+            signal: signal.target_entity(),
+            update: Box::new(move |data: Option<&T>| Some(update(data))),
+        });
+    }
+
+    fn watch_signal<T: LazySignalsData>(
+        &mut self,
+        immutable: impl LazySignalsSource<Option<T>>,
+        watcher: SignalWatcher<T>
+    ) {
+        self.add(WatchSignalCommand::<T> {
+            immutable: immutable.source_entity(),
+            watcher,
+        });
+    }
+}
+
+/// Mark a `Computed`, `Effect`, or `Action` that can never run, instead of panicking when it does.
+fn fault(entity: &mut EntityWorldMut, error: LazySignalsError) {
+    error!("{:?} will not run: {}", entity.id(), error);
+    entity.remove::<InitDependencies>();
+    entity.insert(Faulted { error });
+}
+
+/// Get the `ComponentId` of an observable component and cache its vtable for the systems.
+fn init_observable<T: LazySignalsObservable + Component>(world: &mut World) -> ComponentId {
+    // once init runs once for a concrete `T`, it just returns the existing `ComponentId` next time
+    let component_id = world.init_component::<T>();
+    world.get_resource_or_insert_with(LazySignalsRegistry::default).register::<T>(component_id);
+    component_id
+}
+
+/// Hand the registered `Clone` fn for `T` (if any) to the `LazySignalsState` on the given entity.
+fn init_cloner<T: LazySignalsData>(entity: Entity, world: &mut World) {
+    let Some(cloner) = world
+        .get_resource::<LazySignalsRegistry>()
+        .and_then(|registry| registry.cloner::<T>()) else {
+        return;
+    };
+    if let Some(mut state) = world.get_mut::<LazySignalsState<T>>(entity) {
+        state.set_cloner(cloner);
+    }
+}
+
+/// Check that `P` is the args tuple the function was created with (its closure can not take
+/// anything else) and that the new `sources` line up with it.
+fn validate_rewire<P: LazySignalsArgs>(
+    args_type: TypeId,
+    sources: &[Entity],
+    args_mode: ArgsMode,
+    world: &World
+) -> Result<(), LazySignalsError> {
+    if TypeId::of::<P>() != args_type {
+        return Err(
+            LazySignalsError::InvalidArgs(
+                format!("{} is not the args tuple it was created with", type_name::<P>())
+            )
+        );
+    }
+    validate_args::<P>(sources, args_mode, world)
+}
+
+/// Swap the sources of a `Computed`, `Effect`, or `Action` and mark it to run again during the next
+/// batch with the new args.
+fn rewire<P: LazySignalsArgs>(
+    derived: Entity,
+    rewire_sources: impl FnOnce(&[Entity]) -> Vec<Entity>,
+    world: &mut World
+) {
+    let Some(entity) = world.get_entity(derived) else {
+        error!("could not get Derived");
+        return;
+    };
+
+    // find the current wiring
+    let (old_sources, triggers, args_mode, args_type, is_effect) = if
+        let Some(computed) = entity.get::<ComputedImmutable>()
+    {
+        let sources = computed.sources.clone();
+        (sources, Vec::<Entity>::new(), computed.args_mode, computed.args_type, false)
+    } else if let Some(effect) = entity.get::<LazyEffect>() {
+        let sources = effect.sources.clone();
+        (sources, effect.triggers.clone(), effect.args_mode, effect.args_type, true)
+    } else {
+        error!("{:?} is not a Computed or an Effect", derived);
+        return;
+    };
+
+    let sources = rewire_sources(&old_sources);
+    let validation = validate_rewire::<P>(args_type, &sources, args_mode, world).and_then(|_| {
+        // only a computed can close a cycle, since nothing can depend on an effect
+        if is_effect {
+            return Ok(());
+        }
+        match find_cycle(derived, &sources, world) {
+            Some(path) => Err(LazySignalsError::Cycle(path)),
+            None => Ok(()),
+        }
+    });
+
+    world.resource_scope(|world, registry: Mut<LazySignalsRegistry>| {
+        // stop listening to anything that is no longer a dependency
+        for source in old_sources.iter() {
+            if !sources.contains(source) && !triggers.contains(source) {
+                unsubscribe(&derived, source, &registry, world);
+            }
+        }
+
+        let mut entity = world.entity_mut(derived);
+        if let Some(mut computed) = entity.get_mut::<ComputedImmutable>() {
+            computed.sources = sources;
+        } else if let Some(mut effect) = entity.get_mut::<LazyEffect>() {
+            effect.sources = sources;
+        }
+
+        match validation {
+            Ok(_) => {
+                // init subscribes to the new sources, then the node runs again with the new args
+                // (a dirty computed is picked up by send_signals so its subscribers update too)
+                entity.remove::<Faulted>();
+                entity.insert((InitDependencies, Dirty));
+                if is_effect {
+                    entity.insert((DeferredEffect, Triggered));
+                } else {
+                    entity.insert(ForceCompute);
+                }
+            }
+            Err(error) => {
+                error!("{:?} will not run: {}", derived, error);
+                fault_derived(&derived, error, &registry, world);
+            }
+        }
+    });
+}
+
+/// Command to append a source to a `Computed`, `Effect`, or `Action` on the given entity.
+pub struct AddSourceCommand<P: LazySignalsArgs> {
+    pub derived: Entity,
+    pub source: Entity,
+    pub args_type: PhantomData<P>,
+}
+
+impl<P: LazySignalsArgs> Command for AddSourceCommand<P> {
+    fn apply(self, world: &mut World) {
+        trace!("AddSourceCommand {:?}", self.derived);
+        rewire::<P>(
+            self.derived,
+            |sources| {
+                let mut sources = sources.to_vec();
+                sources.push(self.source);
+                sources
+            },
+            world
+        );
+    }
+}
+
+/// Command to cancel the running, retrying, and queued tasks of an `Action` on the given entity.
+pub struct CancelActionCommand {
+    pub action: Entity,
+}
+
+impl Command for CancelActionCommand {
+    fn apply(self, world: &mut World) {
+        trace!("CancelActionCommand {:?}", self.action);
+        let Some(mut entity) = world.get_entity_mut(self.action) else {
+            return;
+        };
+
+        // dropping the tasks cancels them
+        if entity.take::<RunningTask>().is_none() {
+            return;
+        }
+        entity.remove::<TaskDeadline>();
+        if let Some(resource) = entity.get::<LazyResource>() {
+            (resource.idle)(self.action, world);
+        }
+    }
 }
 
 /// Command to create an action (non-blocking effect) from the given entity.
@@ -118,21 +597,26 @@ pub struct CreateActionCommand<P: LazySignalsArgs> {
     pub function: Mutex<Box<dyn ActionWrapper>>,
     pub sources: Vec<Entity>,
     pub triggers: Vec<Entity>,
+    pub args_mode: ArgsMode,
     pub args_type: PhantomData<P>,
 }
 
 impl<P: LazySignalsArgs> Command for CreateActionCommand<P> {
     fn apply(self, world: &mut World) {
-        world
-            .get_entity_mut(self.effect)
-            .unwrap()
-            .insert(
-                EffectBundle::from_function::<P>(
-                    EffectContext::Long(self.function),
-                    self.sources,
-                    self.triggers
-                )
-            );
+        let validation = validate_args::<P>(&self.sources, self.args_mode, world);
+        let mut entity = world.get_entity_mut(self.effect).unwrap();
+        entity.insert(
+            EffectBundle::from_function::<P>(
+                EffectContext::Long(self.function),
+                self.sources,
+                self.triggers,
+                self.args_mode
+            )
+        );
+
+        if let Err(error) = validation {
+            fault(&mut entity, error);
+        }
     }
 }
 
@@ -141,20 +625,61 @@ pub struct CreateComputedCommand<P: LazySignalsArgs, R: LazySignalsData> {
     pub computed: Entity,
     pub function: Mutex<Box<dyn ComputedContext>>,
     pub sources: Vec<Entity>,
+    pub args_mode: ArgsMode,
     pub args_type: PhantomData<P>,
     pub result_type: PhantomData<R>,
 }
 
 impl<P: LazySignalsArgs, R: LazySignalsData> Command for CreateComputedCommand<P, R> {
     fn apply(self, world: &mut World) {
-        // once init runs once for a concrete `R`, it just returns the existing `ComponentId` next time
-        let component_id = world.init_component::<LazySignalsState<R>>();
-        world
-            .get_entity_mut(self.computed)
-            .unwrap()
-            .insert(
-                ComputedBundle::<R>::from_function::<P>(self.function, self.sources, component_id)
-            );
+        let component_id = init_observable::<LazySignalsState<R>>(world);
+        let validation = validate_args::<P>(&self.sources, self.args_mode, world);
+        let mut entity = world.get_entity_mut(self.computed).unwrap();
+        entity.insert(
+            ComputedBundle::<R>::from_function::<P>(
+                self.function,
+                self.sources,
+                self.args_mode,
+                component_id
+            )
+        );
+
+        // it has no value yet, so it runs even if its sources have not changed
+        entity.insert(ForceCompute);
+
+        if let Err(error) = validation {
+            // store the error as the result so anything downstream sees it too
+            entity
+                .get_mut::<LazySignalsState<R>>()
+                .unwrap()
+                .update(LazySignalsResult { data: None, error: Some(error.clone()) });
+            fault(&mut entity, error);
+        }
+
+        init_cloner::<R>(self.computed, world);
+    }
+}
+
+/// Command to create a computed memo with no sources until it first runs, from the given entity.
+pub struct CreateComputedTrackedCommand<R: LazySignalsData> {
+    pub computed: Entity,
+    pub function: Mutex<Box<dyn ComputedContext>>,
+    pub result_type: PhantomData<R>,
+}
+
+impl<R: LazySignalsData> Command for CreateComputedTrackedCommand<R> {
+    fn apply(self, world: &mut World) {
+        (CreateComputedCommand::<(), R> {
+            computed: self.computed,
+            function: self.function,
+            sources: Vec::new(),
+            args_mode: ArgsMode::Options,
+            args_type: PhantomData,
+            result_type: PhantomData,
+        }).apply(world);
+
+        // nothing will ever tell it to run, so mark it to find its sources during the next batch
+        world.entity_mut(self.computed).insert((TrackedSources, Dirty));
     }
 }
 
@@ -164,21 +689,82 @@ pub struct CreateEffectCommand<P: LazySignalsArgs> {
     pub function: Mutex<Box<dyn EffectWrapper>>,
     pub sources: Vec<Entity>,
     pub triggers: Vec<Entity>,
+    pub args_mode: ArgsMode,
     pub args_type: PhantomData<P>,
 }
 
 impl<P: LazySignalsArgs> Command for CreateEffectCommand<P> {
     fn apply(self, world: &mut World) {
+        let validation = validate_args::<P>(&self.sources, self.args_mode, world);
+        let mut entity = world.get_entity_mut(self.effect).unwrap();
+        entity.insert(
+            EffectBundle::from_function::<P>(
+                EffectContext::Short(self.function),
+                self.sources,
+                self.triggers,
+                self.args_mode
+            )
+        );
+
+        if let Err(error) = validation {
+            fault(&mut entity, error);
+        }
+    }
+}
+
+/// Command to create a `LazyEffect` that runs a one-shot system from the given entity.
+pub struct CreateEffectSystemCommand<P: LazySignalsArgs> {
+    pub effect: Entity,
+    pub system: BoxedSystem<DynamicTuple, ()>,
+    pub sources: Vec<Entity>,
+    pub triggers: Vec<Entity>,
+    pub args_mode: ArgsMode,
+    pub args_type: PhantomData<P>,
+}
+
+impl<P: LazySignalsArgs> Command for CreateEffectSystemCommand<P> {
+    fn apply(self, world: &mut World) {
+        let validation = validate_args::<P>(&self.sources, self.args_mode, world);
+
+        // register once, so the system keeps its state for as long as the effect lives
+        let system_id = world.register_boxed_system(self.system);
+        world.entity_mut(system_id.entity()).insert(OwnedByEffect { effect: self.effect });
+
+        let mut entity = world.get_entity_mut(self.effect).unwrap();
+        entity.insert(
+            EffectBundle::from_function::<P>(
+                EffectContext::System(system_id),
+                self.sources,
+                self.triggers,
+                self.args_mode
+            )
+        );
+
+        if let Err(error) = validation {
+            fault(&mut entity, error);
+        }
+    }
+}
+
+/// Command to create a reducer (`StateBundle` plus `LazyReducer`) from the given entity.
+pub struct CreateReducerCommand<S: LazySignalsData, M: Send + Sync + 'static> {
+    pub reducer: Entity,
+    pub data: S,
+    pub function: Arc<dyn ReducerWrapper<M>>,
+}
+
+impl<S: LazySignalsData, M: Send + Sync + 'static> Command for CreateReducerCommand<S, M> {
+    fn apply(self, world: &mut World) {
+        let component_id = init_observable::<LazySignalsState<S>>(world);
         world
-            .get_entity_mut(self.effect)
+            .get_entity_mut(self.reducer)
             .unwrap()
-            .insert(
-                EffectBundle::from_function::<P>(
-                    EffectContext::Short(self.function),
-                    self.sources,
-                    self.triggers
-                )
-            );
+            .insert((
+                StateBundle::<S>::from_value(self.data, component_id),
+                LazyReducer::<M> { function: self.function },
+            ));
+
+        init_cloner::<S>(self.reducer, world);
     }
 }
 
@@ -190,12 +776,120 @@ pub struct CreateStateCommand<T: LazySignalsData> {
 
 impl<T: LazySignalsData> Command for CreateStateCommand<T> {
     fn apply(self, world: &mut World) {
-        // store the `ComponentId`` so we can reflect the `LazySignalsState` later
-        let component_id = world.init_component::<LazySignalsState<T>>();
+        // store the `ComponentId` so the systems can find the `LazySignalsState` vtable later
+        let component_id = init_observable::<LazySignalsState<T>>(world);
         world
             .get_entity_mut(self.state)
             .unwrap()
             .insert(StateBundle::<T>::from_value(self.data, component_id));
+
+        init_cloner::<T>(self.state, world);
+    }
+}
+
+/// Command to create a `LazySignalsStream` from the given entity.
+pub struct CreateStreamCommand<T: LazySignalsData> {
+    pub stream: Entity,
+    pub data_type: PhantomData<T>,
+}
+
+impl<T: LazySignalsData> Command for CreateStreamCommand<T> {
+    fn apply(self, world: &mut World) {
+        // store the `ComponentId` so the systems can find the `LazySignalsStream` vtable later
+        let component_id = init_observable::<LazySignalsStream<T>>(world);
+        world
+            .get_entity_mut(self.stream)
+            .unwrap()
+            .insert(StreamBundle::<T>::from_component_id(component_id));
+    }
+}
+
+/// Command to dispatch a message to the `LazyReducer` on the given entity.
+pub struct DispatchCommand<M: Send + Sync + 'static> {
+    pub reducer: Entity,
+    pub message: M,
+}
+
+impl<M: Send + Sync + 'static> Command for DispatchCommand<M> {
+    fn apply(self, world: &mut World) {
+        trace!("DispatchCommand {:?}", self.reducer);
+        // we're less sure the reducer actually exists, but don't panic if not
+        if let Some(mut entity) = world.get_entity_mut(self.reducer) {
+            if let Some(reducer) = entity.get::<LazyReducer<M>>() {
+                let function = reducer.function.clone();
+                function(self.message, &mut entity);
+                trace!("dispatched message");
+            } else {
+                error!("could not get Reducer");
+            }
+        } else {
+            error!("could not get Signal");
+        }
+    }
+}
+
+/// Command to register a direct `Clone` fn for a data type.
+pub struct RegisterCloneCommand<T: LazySignalsData + Clone> {
+    pub data_type: PhantomData<T>,
+}
+
+impl<T: LazySignalsData + Clone> Command for RegisterCloneCommand<T> {
+    fn apply(self, world: &mut World) {
+        trace!("RegisterCloneCommand {}", std::any::type_name::<T>());
+        world.get_resource_or_insert_with(LazySignalsRegistry::default).register_clone::<T>();
+    }
+}
+
+/// Command to remove a source from a `Computed`, `Effect`, or `Action` on the given entity.
+pub struct RemoveSourceCommand<P: LazySignalsArgs> {
+    pub derived: Entity,
+    pub source: Entity,
+    pub args_type: PhantomData<P>,
+}
+
+impl<P: LazySignalsArgs> Command for RemoveSourceCommand<P> {
+    fn apply(self, world: &mut World) {
+        trace!("RemoveSourceCommand {:?}", self.derived);
+        rewire::<P>(
+            self.derived,
+            |sources| {
+                sources
+                    .iter()
+                    .filter(|source| **source != self.source)
+                    .copied()
+                    .collect()
+            },
+            world
+        );
+    }
+}
+
+/// Command to put a `Signal` into an error state during the next tick.
+pub struct SendErrorCommand<T: LazySignalsData> {
+    pub signal: Entity,
+    pub error: LazySignalsError,
+    pub data_type: PhantomData<T>,
+}
+
+impl<T: LazySignalsData> Command for SendErrorCommand<T> {
+    fn apply(self, world: &mut World) {
+        trace!("SendErrorCommand {:?}", self.signal);
+        // we're less sure the signal actually exists, but don't panic if not
+        // (assume the caller removed it and we don't care about it anymore)
+        if let Some(mut entity) = world.get_entity_mut(self.signal) {
+            if let Some(mut immutable) = entity.get_mut::<LazySignalsState<T>>() {
+                immutable.merge_next(
+                    LazySignalsResult { data: None, error: Some(self.error) },
+                    false
+                );
+                entity.insert(SendSignal);
+                trace!("merged error and inserted SendSignal");
+            } else {
+                error!("could not get State");
+            }
+        } else {
+            error!("could not get Signal");
+        }
     }
 }
 
@@ -227,6 +921,41 @@ impl<T: LazySignalsData> Command for SendSignalCommand<T> {
     }
 }
 
+/// Command to replace the `LazySignalsEquality` policy of a `LazySignalsState` on the given entity.
+pub struct SetEqualityCommand<T: LazySignalsData> {
+    pub immutable: Entity,
+    pub equality: LazySignalsEquality<T>,
+}
+
+impl<T: LazySignalsData> Command for SetEqualityCommand<T> {
+    fn apply(self, world: &mut World) {
+        trace!("SetEqualityCommand {:?}", self.immutable);
+        if let Some(mut entity) = world.get_entity_mut(self.immutable) {
+            if let Some(mut immutable) = entity.get_mut::<LazySignalsState<T>>() {
+                immutable.set_equality(self.equality);
+            } else {
+                error!("could not get State");
+            }
+        } else {
+            error!("could not get Signal");
+        }
+    }
+}
+
+/// Command to replace the sources of a `Computed`, `Effect`, or `Action` on the given entity.
+pub struct SetSourcesCommand<P: LazySignalsArgs> {
+    pub derived: Entity,
+    pub sources: Vec<Entity>,
+    pub args_type: PhantomData<P>,
+}
+
+impl<P: LazySignalsArgs> Command for SetSourcesCommand<P> {
+    fn apply(self, world: &mut World) {
+        trace!("SetSourcesCommand {:?}", self.derived);
+        rewire::<P>(self.derived, |_| self.sources, world);
+    }
+}
+
 /// Command to trigger a `Signal` (i.e. send signal even if value unchanged) to the given entity.
 pub struct TriggerSignalCommand<T: LazySignalsData> {
     pub signal: Entity,
@@ -254,3 +983,313 @@ impl<T: LazySignalsData> Command for TriggerSignalCommand<T> {
         }
     }
 }
+
+/// Command to append a value to a `LazySignalsStream` (i.e. deliver it during the next tick).
+pub struct SendToStreamCommand<T: LazySignalsData> {
+    pub stream: Entity,
+    pub data: T,
+}
+
+impl<T: LazySignalsData> Command for SendToStreamCommand<T> {
+    fn apply(self, world: &mut World) {
+        trace!("SendToStreamCommand {:?}", self.stream);
+        // we're less sure the stream actually exists, but don't panic if not
+        // (assume the caller removed it and we don't care about it anymore)
+        if let Some(mut entity) = world.get_entity_mut(self.stream) {
+            if let Some(mut stream) = entity.get_mut::<LazySignalsStream<T>>() {
+                stream.push(self.data);
+                entity.insert(SendSignal);
+                trace!("pushed next and inserted SendSignal");
+            } else {
+                error!("could not get Stream");
+            }
+        } else {
+            error!("could not get Signal");
+        }
+    }
+}
+
+/// Command to queue an update of a `Signal` that is folded into its next value during the next tick.
+/// Every update sent in the same tick is applied in order, so none of them are lost.
+pub struct UpdateSignalCommand<T: LazySignalsData> {
+    pub signal: Entity,
+    pub update: Box<dyn UpdaterWrapper<T>>,
+}
+
+impl<T: LazySignalsData> Command for UpdateSignalCommand<T> {
+    fn apply(self, world: &mut World) {
+        trace!("UpdateSignalCommand {:?}", self.signal);
+        // we're less sure the signal actually exists, but don't panic if not
+        // (assume the caller removed it and we don't care about it anymore)
+        if let Some(mut entity) = world.get_entity_mut(self.signal) {
+            if let Some(mut immutable) = entity.get_mut::<LazySignalsState<T>>() {
+                immutable.merge_update(self.update);
+                entity.insert(SendSignal);
+                trace!("merged update and inserted SendSignal");
+            } else {
+                error!("could not get State");
+            }
+        } else {
+            error!("could not get Signal");
+        }
+    }
+}
+
+/// Command to attach the `SignalWatcher` of a `SignalStream` to the `LazySignalsState` on the given
+/// entity. If there is no such state, the watcher is dropped, which ends the stream.
+pub struct WatchSignalCommand<T: LazySignalsData> {
+    pub immutable: Entity,
+    pub watcher: SignalWatcher<T>,
+}
+
+impl<T: LazySignalsData> Command for WatchSignalCommand<T> {
+    fn apply(self, world: &mut World) {
+        trace!("WatchSignalCommand {:?}", self.immutable);
+        if let Some(mut entity) = world.get_entity_mut(self.immutable) {
+            if let Some(mut immutable) = entity.get_mut::<LazySignalsState<T>>() {
+                immutable.watch(self.watcher);
+            } else {
+                error!("could not get State");
+            }
+        } else {
+            error!("could not get Signal");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use crate::{ prelude::*, testing::*, StaticStrRef };
+
+    #[test]
+    fn set_sources_rejects_a_cycle() {
+        let mut app = app();
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let a = LazySignals.state::<u32>(1, &mut commands);
+        let x = LazySignals.computed::<(Option<u32>,), u32>(
+            |(a,)| LazySignals::result(a.unwrap_or(0) + 1),
+            (a,),
+            &mut commands
+        );
+        let y = LazySignals.computed::<(Option<u32>,), u32>(
+            |(x,)| LazySignals::result(x.unwrap_or(0) + 1),
+            (x,),
+            &mut commands
+        );
+        update(&mut app);
+
+        LazySignals.set_sources::<(Option<u32>,)>(x, (y,), &mut app.world_mut().commands());
+        update(&mut app);
+
+        assert_eq!(
+            LazySignals.get_error::<u32>(x, app.world()),
+            Some(LazySignalsError::Cycle(vec![x.entity(), y.entity(), x.entity()]))
+        );
+
+        // only the computed that closed the cycle is rejected
+        assert!(app.world().get::<Faulted>(y.entity()).is_none());
+    }
+
+    #[test]
+    fn invalid_args_fault_instead_of_panicking() {
+        let mut app = app();
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let name = LazySignals.state::<StaticStrRef>("name", &mut commands);
+        let count = LazySignals.state::<u32>(1, &mut commands);
+
+        // the wrong type
+        let memo = LazySignals.computed::<(Option<u32>,), u32>(
+            |(count,)| LazySignals::result(count.unwrap()),
+            vec![name.entity()],
+            &mut commands
+        );
+
+        // the wrong number of sources
+        let effect = LazySignals.effect::<(Option<u32>,)>(
+            |(count,), _world| {
+                count.unwrap();
+                None
+            },
+            vec![count.entity(), name.entity()],
+            vec![],
+            &mut commands
+        );
+        update(&mut app);
+
+        // they never run, even when their sources change
+        let mut commands = app.world_mut().commands();
+        LazySignals.send(name, "other", &mut commands);
+        LazySignals.send(count, 2, &mut commands);
+        update(&mut app);
+
+        assert!(
+            matches!(
+                LazySignals.get_error::<u32>(memo, app.world()),
+                Some(LazySignalsError::InvalidArgs(_))
+            )
+        );
+        assert!(
+            matches!(
+                app.world().get::<Faulted>(effect.entity()),
+                Some(Faulted { error: LazySignalsError::InvalidArgs(_) })
+            )
+        );
+    }
+
+    type Pair = (Option<u32>, Option<u32>);
+
+    // a computed that adds up a pair of states, wired to the given number of them
+    fn sum_of(count: usize, app: &mut App) -> (Vec<Entity>, Memo<u32>) {
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let states = (1..=3)
+            .map(|value| LazySignals.state::<u32>(value, &mut commands).entity())
+            .collect::<Vec<_>>();
+        let sum = LazySignals.computed::<Pair, u32>(
+            |(a, b)| LazySignals::result(a.unwrap_or(0) + b.unwrap_or(0)),
+            states[..count].to_vec(),
+            &mut commands
+        );
+        update(app);
+        (states, sum)
+    }
+
+    #[test]
+    fn add_source_fills_in_a_missing_arg() {
+        let mut app = app();
+        let (states, sum) = sum_of(1, &mut app);
+        assert!(app.world().get::<Faulted>(sum.entity()).is_some());
+
+        LazySignals.add_source::<Pair>(sum, states[1], &mut app.world_mut().commands());
+        update(&mut app);
+
+        assert!(app.world().get::<Faulted>(sum.entity()).is_none());
+        assert_eq!(LazySignals.read::<u32>(sum, app.world()), Some(3));
+    }
+
+    #[test]
+    fn add_source_rejects_other_args() {
+        let mut app = app();
+        let (states, sum) = sum_of(2, &mut app);
+
+        LazySignals.add_source::<(Option<u32>, Option<u32>, Option<u32>)>(
+            sum,
+            states[2],
+            &mut app.world_mut().commands()
+        );
+        update(&mut app);
+        update(&mut app);
+
+        assert!(app.world().get::<Faulted>(sum.entity()).is_some());
+        assert!(
+            matches!(
+                LazySignals.get_error::<u32>(sum, app.world()),
+                Some(LazySignalsError::InvalidArgs(_))
+            )
+        );
+    }
+
+    #[test]
+    fn remove_source_drops_an_extra_arg() {
+        let mut app = app();
+        let (states, sum) = sum_of(3, &mut app);
+        assert!(app.world().get::<Faulted>(sum.entity()).is_some());
+
+        LazySignals.remove_source::<Pair>(sum, states[0], &mut app.world_mut().commands());
+        update(&mut app);
+
+        assert!(app.world().get::<Faulted>(sum.entity()).is_none());
+        assert_eq!(LazySignals.read::<u32>(sum, app.world()), Some(5));
+    }
+
+    #[test]
+    fn remove_source_rejects_other_args() {
+        let mut app = app();
+        let (states, sum) = sum_of(2, &mut app);
+
+        // the arity fits, but the closure still takes a pair
+        LazySignals.remove_source::<(Option<u32>,)>(sum, states[1], &mut app.world_mut().commands());
+        update(&mut app);
+        update(&mut app);
+
+        assert!(app.world().get::<Faulted>(sum.entity()).is_some());
+        assert!(
+            matches!(
+                LazySignals.get_error::<u32>(sum, app.world()),
+                Some(LazySignalsError::InvalidArgs(_))
+            )
+        );
+    }
+
+    enum Counter {
+        Add(u32),
+        Reset,
+    }
+
+    fn counter(app: &mut App) -> Signal<u32> {
+        let counter = LazySignals.reducer::<u32, Counter>(
+            0,
+            |count, message| {
+                match message {
+                    Counter::Add(amount) => count + amount,
+                    Counter::Reset => 0,
+                }
+            },
+            &mut app.world_mut().commands()
+        );
+        update(app);
+        counter
+    }
+
+    #[test]
+    fn reducer_folds_every_message_in_order() {
+        let mut app = app();
+        let counter = counter(&mut app);
+
+        let mut commands = app.world_mut().commands();
+        LazySignals.dispatch(counter, Counter::Add(2), &mut commands);
+        LazySignals.dispatch(counter, Counter::Reset, &mut commands);
+        LazySignals.dispatch(counter, Counter::Add(3), &mut commands);
+        LazySignals.dispatch(counter, Counter::Add(4), &mut commands);
+        update(&mut app);
+
+        assert_eq!(LazySignals.read::<u32>(counter, app.world()), Some(7));
+    }
+
+    #[test]
+    fn reducer_drops_messages_while_it_has_no_data() {
+        let mut app = app();
+        let counter = counter(&mut app);
+
+        LazySignals.send_error::<u32>(
+            counter,
+            LazySignalsError::user("offline"),
+            &mut app.world_mut().commands()
+        );
+        update(&mut app);
+
+        LazySignals.dispatch(counter, Counter::Add(2), &mut app.world_mut().commands());
+        update(&mut app);
+
+        assert_eq!(LazySignals.read::<u32>(counter, app.world()), None);
+        assert_eq!(
+            LazySignals.get_error::<u32>(counter, app.world()),
+            Some(LazySignalsError::user("offline"))
+        );
+
+        // it picks up again once it has data
+        let mut commands = app.world_mut().commands();
+        LazySignals.send(counter, 1, &mut commands);
+        update(&mut app);
+        LazySignals.dispatch(counter, Counter::Add(2), &mut app.world_mut().commands());
+        update(&mut app);
+
+        assert_eq!(LazySignals.read::<u32>(counter, app.world()), Some(3));
+    }
+}
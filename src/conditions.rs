@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+
+use crate::{ framework::*, handles::LazySignalsSource, params::SignalReader };
+
+/// ## Run Conditions
+/// Gate ordinary systems on reactive state, e.g.
+/// `app.add_systems(Update, find_path.run_if(signal_changed(target)))`.
+///
+/// The markers these read are set while the `LazySignals` systems run and cleared at the start of
+/// the next batch, so a condition evaluated after `PreUpdate` sees what happened in this batch.
+///
+/// Returns `true` if the `Signal` or `Computed` changed value during the last batch.
+pub fn signal_changed(
+    immutable: impl Into<Entity>
+) -> impl FnMut(Query<(), With<ValueChanged>>) -> bool + Clone {
+    let entity = immutable.into();
+    move |query: Query<(), With<ValueChanged>>| query.contains(entity)
+}
+
+/// Returns `true` if the current value of the `Signal` or `Computed` equals the given value.
+pub fn signal_equals<T: LazySignalsData>(
+    immutable: impl LazySignalsSource<Option<T>>,
+    data: T
+) -> impl FnMut(SignalReader<T>) -> bool {
+    let entity = immutable.source_entity();
+    move |reader: SignalReader<T>| reader.read(entity).is_some_and(|value| value == data)
+}
+
+/// Returns `true` if the current value of the `bool` `Signal` or `Computed` is `true`.
+pub fn signal_is_truthy(
+    immutable: impl LazySignalsSource<Option<bool>>
+) -> impl FnMut(SignalReader<bool>) -> bool + Clone {
+    let entity = immutable.source_entity();
+    move |reader: SignalReader<bool>| reader.read(entity).unwrap_or(false)
+}
+
+/// Returns `true` if the `Signal` was sent with a trigger (e.g. `trigger` or `send_and_trigger`)
+/// during the last batch, whether or not its value changed.
+pub fn signal_triggered(
+    immutable: impl Into<Entity>
+) -> impl FnMut(Query<(), With<Triggered>>) -> bool + Clone {
+    let entity = immutable.into();
+    move |query: Query<(), With<Triggered>>| query.contains(entity)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ prelude::*, testing::* };
+
+    use super::*;
+
+    #[derive(Resource, Default)]
+    struct Runs {
+        changed: u32,
+        equals: u32,
+        truthy: u32,
+        triggered: u32,
+    }
+
+    #[test]
+    fn conditions_follow_the_last_batch() {
+        let mut app = app();
+        app.init_resource::<Runs>();
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let count = LazySignals.state::<u32>(0, &mut commands);
+        let flag = LazySignals.state::<bool>(false, &mut commands);
+        let trigger = LazySignals.state::<()>((), &mut commands);
+        app.add_systems(
+            Update,
+            (
+                (|mut runs: ResMut<Runs>| runs.changed += 1).run_if(signal_changed(count)),
+                (|mut runs: ResMut<Runs>| runs.equals += 1).run_if(signal_equals(count, 2)),
+                (|mut runs: ResMut<Runs>| runs.truthy += 1).run_if(signal_is_truthy(flag)),
+                (|mut runs: ResMut<Runs>| runs.triggered += 1).run_if(signal_triggered(trigger)),
+            )
+        );
+        update(&mut app);
+
+        let mut commands = app.world_mut().commands();
+        LazySignals.send(count, 2, &mut commands);
+        LazySignals.send(flag, true, &mut commands);
+        LazySignals.trigger(trigger, &mut commands);
+        update(&mut app);
+
+        // nothing new is sent, so only the conditions on the current values still hold
+        update(&mut app);
+
+        let runs = app.world().resource::<Runs>();
+        assert_eq!((runs.changed, runs.equals, runs.truthy, runs.triggered), (1, 2, 2, 1));
+    }
+}
@@ -1,5 +1,5 @@
 use bevy::prelude::*;
-use crate::{ framework::*, lazy_immutable::LazySignalsState };
+use crate::{ framework::*, lazy_immutable::LazySignalsState, lazy_stream::LazySignalsStream };
 
 /// ## Bundles
 #[derive(Bundle)]
@@ -14,6 +14,7 @@ impl<R: LazySignalsData> ComputedBundle<R> {
     pub fn from_function<P: LazySignalsArgs>(
         function: Mutex<Box<dyn ComputedContext>>,
         sources: Vec<Entity>,
+        args_mode: ArgsMode,
         component_id: ComponentId
     ) -> ComputedBundle<R> {
         ComputedBundle::<R> {
@@ -21,10 +22,11 @@ impl<R: LazySignalsData> ComputedBundle<R> {
                 data: None,
                 error: None,
             }),
-            meta: ImmutableState { component_id },
+            meta: ImmutableState::for_state::<R>(component_id),
             context: ComputedImmutable {
                 function,
                 sources,
+                args_mode,
                 args_type: TypeId::of::<P>(),
                 result_type: TypeId::of::<LazySignalsState<R>>(),
             },
@@ -43,13 +45,15 @@ impl EffectBundle {
     pub fn from_function<P: LazySignalsArgs>(
         function: EffectContext,
         sources: Vec<Entity>,
-        triggers: Vec<Entity>
+        triggers: Vec<Entity>,
+        args_mode: ArgsMode
     ) -> EffectBundle {
         EffectBundle {
             context: LazyEffect {
                 function,
                 sources,
                 triggers,
+                args_mode,
                 args_type: TypeId::of::<P>(),
             },
             init: InitDependencies,
@@ -70,7 +74,24 @@ impl<T: LazySignalsData> StateBundle<T> {
                 data: Some(data),
                 error: None,
             }),
-            meta: ImmutableState { component_id },
+            meta: ImmutableState::for_state::<T>(component_id),
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct StreamBundle<T: LazySignalsData> {
+    stream: LazySignalsStream<T>,
+    meta: ImmutableState,
+    marker: LazyStream,
+}
+
+impl<T: LazySignalsData> StreamBundle<T> {
+    pub fn from_component_id(component_id: ComponentId) -> StreamBundle<T> {
+        StreamBundle {
+            stream: LazySignalsStream::<T>::new(),
+            meta: ImmutableState::for_stream::<T>(component_id),
+            marker: LazyStream,
         }
     }
 }
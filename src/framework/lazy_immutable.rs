@@ -1,6 +1,6 @@
 use bevy::{ prelude::*, reflect::{ reflect_trait, DynamicTuple, Reflect } };
 
-use crate::arcane_wizardry::{ clone_data, insert_data };
+use crate::{ arcane_wizardry::{ clone_data, insert_data }, signal_stream::SignalWatcher };
 
 use super::*;
 
@@ -16,6 +16,9 @@ pub trait LazySignalsImmutable: Send + Sync + 'static {
     /// Provide a new value for the lazy update system to merge.
     fn merge_next(&mut self, next: LazySignalsResult<Self::DataType>, trigger: bool);
 
+    /// Queue an update to be folded into the next value, in order, when the signal is sent.
+    fn merge_update(&mut self, update: Box<dyn UpdaterWrapper<Self::DataType>>);
+
     /// Immediately update a new value without triggering any subscribers (mostly used internally).
     fn update(&mut self, next: LazySignalsResult<Self::DataType>) -> bool;
 
@@ -38,17 +41,32 @@ pub trait LazySignalsObservable {
     /// Copy the data into a dynamic tuple of args for the `Effect` or `Computed` to consume.
     fn copy_data(&mut self, caller: Entity, args: &mut DynamicTuple);
 
+    /// Get a copy of the current error, if any, so it can be passed downstream.
+    fn copy_error(&self) -> Option<LazySignalsError>;
+
+    /// Copy the data or error into a dynamic tuple of results args for the `Effect` or `Computed`.
+    fn copy_result(&mut self, caller: Entity, args: &mut DynamicTuple);
+
+    /// Called at the end of a tick to discard any values that only live for that tick.
+    fn drain(&mut self);
+
     /// Get the list of subscribers that may need notification.
     fn get_subscribers(&self) -> Vec<Entity>;
 
     /// This method merges the `next_value` and returns `get_subscribers()`.
     fn merge(&mut self) -> MaybeFlaggedEntities;
 
+    /// Provide an error for the lazy update system to merge, without knowing the concrete type.
+    fn merge_error(&mut self, error: LazySignalsError);
+
     /// Called by a lazy update system to refresh the subscribers.
     fn merge_subscribers(&mut self);
 
     /// Called by an `Effect` or `Memo` indirectly by reading the current value.
     fn subscribe(&mut self, entity: Entity);
+
+    /// Called by the disposal system to forget a subscriber that was despawned.
+    fn unsubscribe(&mut self, entity: Entity);
 }
 
 /// A `LazySignalsState` is known as a cell in a propagator network. It may also be referred to as
@@ -73,6 +91,14 @@ pub struct LazySignalsState<T: LazySignalsData> {
     subscribers: EntitySet,
     #[reflect(ignore)]
     next_subscribers: EntitySet,
+    #[reflect(ignore)]
+    equality: LazySignalsEquality<T>,
+    #[reflect(ignore)]
+    updates: Vec<Box<dyn UpdaterWrapper<T>>>,
+    #[reflect(ignore)]
+    cloner: Option<fn(&T) -> T>,
+    #[reflect(ignore)]
+    watchers: Vec<SignalWatcher<T>>,
 }
 
 impl<T: LazySignalsData> LazySignalsState<T> {
@@ -86,6 +112,96 @@ impl<T: LazySignalsData> LazySignalsState<T> {
             triggered: false,
             subscribers: empty_set(),
             next_subscribers: empty_set(),
+            equality: LazySignalsEquality::default(),
+            updates: Vec::new(),
+            cloner: None,
+            watchers: Vec::new(),
+        }
+    }
+
+    /// Feed every later change of the value to the `SignalStream` of the given watcher.
+    pub fn watch(&mut self, watcher: SignalWatcher<T>) {
+        self.watchers.push(watcher);
+    }
+
+    /// Copy values with a direct `Clone` instead of going through `FromReflect`.
+    pub fn set_cloner(&mut self, cloner: fn(&T) -> T) {
+        self.cloner = Some(cloner);
+    }
+
+    /// Replace the policy used to decide whether a new value is a change.
+    pub fn set_equality(&mut self, equality: LazySignalsEquality<T>) {
+        self.equality = equality;
+    }
+
+    /// Copy the current result, taking the `Clone` fast path if there is one.
+    fn clone_result(&self) -> LazySignalsResult<T> {
+        match self.cloner {
+            Some(cloner) =>
+                LazySignalsResult {
+                    data: self.result.data.as_ref().map(cloner),
+                    error: self.result.error.clone(),
+                },
+            None => clone_data(&self.result),
+        }
+    }
+
+    /// Fold the queued updates into the `next_value`, starting from the pending value if a signal
+    /// was sent this tick, or the current value if not. An error sent this tick wins and the
+    /// updates are dropped.
+    fn fold_updates(&mut self) {
+        let pending = match &self.next_value.error {
+            None => true,
+            Some(LazySignalsError::NoNextValue) => false,
+            Some(_) => {
+                self.updates.clear();
+                return;
+            }
+        };
+        let mut next = if pending {
+            self.next_value.data.take()
+        } else {
+            self.clone_result().data
+        };
+
+        let mut updated = pending;
+        for update in self.updates.drain(..) {
+            if let Some(value) = update(next.as_ref()) {
+                next = Some(value);
+                updated = true;
+            }
+        }
+
+        if updated {
+            self.next_value = LazySignalsResult { data: next, error: None };
+        }
+    }
+
+    /// Send the current value to each `SignalStream` that is still around and forget the rest.
+    fn notify_watchers(&mut self) {
+        if self.watchers.is_empty() {
+            return;
+        }
+        let watchers = std::mem::take(&mut self.watchers);
+        self.watchers = watchers
+            .into_iter()
+            .filter(|watcher| watcher.is_watched())
+            .collect();
+        for watcher in self.watchers.iter() {
+            watcher.send(self.clone_result().data);
+        }
+    }
+
+    /// Returns `true` if the next result is different from the current one, according to the
+    /// equality policy.
+    fn is_changed(&self, next: &LazySignalsResult<T>) -> bool {
+        if self.result.error != next.error {
+            return true;
+        }
+        match (&self.result.data, &next.data) {
+            (Some(data), Some(next)) => !self.equality.equals(data, next),
+            (None, None) => false,
+            _ => true,
         }
     }
 }
@@ -94,21 +210,30 @@ impl<T: LazySignalsData> LazySignalsImmutable for LazySignalsState<T> {
     type DataType = T;
 
     fn error(&self) -> Option<LazySignalsError> {
-        clone_data(&self.result).error
+        self.result.error.clone()
     }
 
     fn get(&self) -> Option<Self::DataType> {
-        clone_data(&self.result).data
+        self.clone_result().data
     }
 
     fn merge_next(&mut self, next_value: LazySignalsResult<T>, triggered: bool) {
+        // any updates queued before this are overwritten along with the previous next value
+        self.updates.clear();
         self.next_value = next_value;
         self.triggered = triggered;
     }
 
+    fn merge_update(&mut self, update: Box<dyn UpdaterWrapper<T>>) {
+        self.updates.push(update);
+    }
+
     fn update(&mut self, next: LazySignalsResult<Self::DataType>) -> bool {
-        let changed = self.result != next;
+        let changed = self.is_changed(&next);
         self.result = next;
+        if changed {
+            self.notify_watchers();
+        }
         changed
     }
 }
@@ -119,11 +244,25 @@ impl<T: LazySignalsData> LazySignalsObservable for LazySignalsState<T> {
     }
 
     fn copy_data(&mut self, caller: Entity, args: &mut DynamicTuple) {
-        insert_data(args, &self.result);
+        insert_data(args, self.clone_result());
 
         self.subscribe(caller);
     }
 
+    fn copy_error(&self) -> Option<LazySignalsError> {
+        self.result.error.clone()
+    }
+
+    fn copy_result(&mut self, caller: Entity, args: &mut DynamicTuple) {
+        args.insert::<Result<Option<T>, LazySignalsError>>(self.clone_result().into());
+
+        self.subscribe(caller);
+    }
+
+    fn drain(&mut self) {
+        // a state keeps its value until the next signal is sent
+    }
+
     fn get_subscribers(&self) -> Vec<Entity> {
         let mut subs = Vec::<Entity>::new();
 
@@ -134,6 +273,11 @@ impl<T: LazySignalsData> LazySignalsObservable for LazySignalsState<T> {
     }
 
     fn merge(&mut self) -> MaybeFlaggedEntities {
+        // apply any functional updates before deciding what changed
+        if !self.updates.is_empty() {
+            self.fold_updates();
+        }
+
         let mut changed = false;
         let triggered = self.triggered;
 
@@ -145,12 +289,14 @@ impl<T: LazySignalsData> LazySignalsObservable for LazySignalsState<T> {
             Some(err) =>
                 // always merge errors
                 match err {
-                    LazySignalsError::NoSignalError => true,
-
                     // not a real error, nothing to merge...
                     LazySignalsError::NoNextValue => false,
 
-                    LazySignalsError::ReadError(_) => true,
+                    // a different error is a change that subscribers need to know about
+                    _ => {
+                        changed = self.result.error.as_ref() != Some(err);
+                        true
+                    }
                 }
             None =>
                 // if there is no error, then compare the data values
@@ -160,8 +306,8 @@ impl<T: LazySignalsData> LazySignalsObservable for LazySignalsState<T> {
                         match &self.result.data {
                             Some(data) => {
                                 trace!("data exists");
-                                if data != next {
-                                    // the new data is different
+                                if !self.equality.equals(data, next) {
+                                    // the new data is different (or the policy says it always is)
                                     trace!("data != next");
                                     changed = true;
                                     true
@@ -182,13 +328,16 @@ impl<T: LazySignalsData> LazySignalsObservable for LazySignalsState<T> {
                 }
         };
 
-        // overwrite the value
+        // overwrite the value (no need to copy it, the next value is not used again)
         if doo_eet {
-            self.result = clone_data(&self.next_value);
-            self.next_value = LazySignalsResult {
+            self.result = std::mem::replace(&mut self.next_value, LazySignalsResult {
                 data: None,
                 error: Some(LazySignalsError::NoNextValue),
-            };
+            });
+        }
+
+        if changed {
+            self.notify_watchers();
         }
 
         // return a list of subscribers
@@ -206,6 +355,10 @@ impl<T: LazySignalsData> LazySignalsObservable for LazySignalsState<T> {
         Some((subs, changed, triggered))
     }
 
+    fn merge_error(&mut self, error: LazySignalsError) {
+        self.merge_next(LazySignalsResult { data: None, error: Some(error) }, false);
+    }
+
     fn merge_subscribers(&mut self) {
         for subscriber in self.next_subscribers.indices() {
             self.subscribers.insert(subscriber, ());
@@ -216,4 +369,70 @@ impl<T: LazySignalsData> LazySignalsObservable for LazySignalsState<T> {
     fn subscribe(&mut self, entity: Entity) {
         self.next_subscribers.insert(entity, ());
     }
+
+    fn unsubscribe(&mut self, entity: Entity) {
+        self.subscribers.remove(entity);
+        self.next_subscribers.remove(entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(value: u32) -> LazySignalsState<u32> {
+        LazySignalsState::new(LazySignalsResult { data: Some(value), error: None })
+    }
+
+    fn add(amount: u32) -> Box<dyn UpdaterWrapper<u32>> {
+        Box::new(move |value: Option<&u32>| value.map(|value| value + amount))
+    }
+
+    #[test]
+    fn equal_value_is_not_a_change() {
+        let mut state = state(1);
+        state.merge_next(LazySignalsResult { data: Some(1), error: None }, false);
+        assert_eq!(state.merge().map(|(_, changed, _)| changed), Some(false));
+
+        state.set_equality(LazySignalsEquality::Always);
+        state.merge_next(LazySignalsResult { data: Some(1), error: None }, false);
+        assert_eq!(state.merge().map(|(_, changed, _)| changed), Some(true));
+    }
+
+    #[test]
+    fn updates_fold_over_the_current_value() {
+        let mut state = state(1);
+        state.merge_update(add(1));
+        state.merge_update(add(10));
+        state.merge();
+        assert_eq!(state.get(), Some(12));
+    }
+
+    #[test]
+    fn updates_fold_over_the_pending_value() {
+        let mut state = state(1);
+        state.merge_next(LazySignalsResult { data: Some(5), error: None }, false);
+        state.merge_update(add(1));
+        state.merge();
+        assert_eq!(state.get(), Some(6));
+    }
+
+    #[test]
+    fn pending_error_is_kept() {
+        let mut state = state(1);
+        state.merge_error(LazySignalsError::ReadError(Entity::PLACEHOLDER));
+        state.merge_update(add(1));
+        state.merge();
+        assert_eq!(state.error(), Some(LazySignalsError::ReadError(Entity::PLACEHOLDER)));
+        assert_eq!(state.get(), None);
+    }
+
+    #[test]
+    fn send_drops_earlier_updates() {
+        let mut state = state(1);
+        state.merge_update(add(1));
+        state.merge_next(LazySignalsResult { data: Some(5), error: None }, false);
+        state.merge();
+        assert_eq!(state.get(), Some(5));
+    }
 }
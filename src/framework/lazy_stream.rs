@@ -0,0 +1,133 @@
+use bevy::{ prelude::*, reflect::{ DynamicTuple, Reflect } };
+
+use crate::{
+    arcane_wizardry::clone_items,
+    lazy_immutable::{ LazySignalsObservable, ReflectLazySignalsObservable },
+};
+
+use super::*;
+
+/// A `LazySignalsStream` is the non-lossy sibling of `LazySignalsState`. Instead of overwriting a
+/// single `next_value`, every value sent during a tick is collected, and the `Computed`s, `Effect`s,
+/// and `Action`s that use the stream as a source receive a `Vec<T>` instead of an `Option<T>`.
+///
+/// The collected values are kept for the rest of the tick and drained by the first pass of the
+/// next one, before anything new is merged.
+///
+/// As with `LazySignalsState`, the concrete `LazySignalsStream<T>` does not need to be registered
+/// for reflection, since the create command adds it to the `LazySignalsRegistry`.
+#[derive(Component, Reflect)]
+#[reflect(Component, LazySignalsObservable)]
+pub struct LazySignalsStream<T: LazySignalsData> {
+    values: Vec<T>,
+    next_values: Vec<T>,
+    #[reflect(ignore)]
+    subscribers: EntitySet,
+    #[reflect(ignore)]
+    next_subscribers: EntitySet,
+}
+
+impl<T: LazySignalsData> Default for LazySignalsStream<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: LazySignalsData> LazySignalsStream<T> {
+    pub fn new() -> Self {
+        Self {
+            values: Vec::<T>::new(),
+            next_values: Vec::<T>::new(),
+            subscribers: empty_set(),
+            next_subscribers: empty_set(),
+        }
+    }
+
+    /// Provide a new value to be appended during the next batch.
+    pub fn push(&mut self, next: T) {
+        self.next_values.push(next);
+    }
+
+    /// Called by a developer to get every value delivered by the stream during the current tick.
+    pub fn values(&self) -> Vec<T> {
+        clone_items(&self.values)
+    }
+}
+
+impl<T: LazySignalsData> LazySignalsObservable for LazySignalsStream<T> {
+    fn append_none(&mut self, args: &mut DynamicTuple) {
+        args.insert(Vec::<T>::new());
+    }
+
+    fn copy_data(&mut self, caller: Entity, args: &mut DynamicTuple) {
+        args.insert(clone_items(&self.values));
+
+        self.subscribe(caller);
+    }
+
+    fn copy_error(&self) -> Option<LazySignalsError> {
+        // streams only carry values
+        None
+    }
+
+    fn copy_result(&mut self, caller: Entity, args: &mut DynamicTuple) {
+        args.insert::<Result<Vec<T>, LazySignalsError>>(Ok(clone_items(&self.values)));
+
+        self.subscribe(caller);
+    }
+
+    fn drain(&mut self) {
+        self.values.clear();
+    }
+
+    fn get_subscribers(&self) -> Vec<Entity> {
+        let mut subs = Vec::<Entity>::new();
+
+        // copy the subscribers into the output vector
+        subs.extend(self.subscribers.indices());
+        trace!("-found subs {:?}", self.subscribers);
+        subs
+    }
+
+    fn merge(&mut self) -> MaybeFlaggedEntities {
+        // every value sent is a change, even if it is the same as the one before it
+        let changed = !self.next_values.is_empty();
+
+        // output vector for downstream subscribers to process next
+        let mut subs = Vec::<Entity>::new();
+
+        if changed {
+            // keep anything already merged this tick (e.g. when flushing more than once per tick)
+            self.values.append(&mut self.next_values);
+
+            // copy the subscribers into the output vector
+            subs = self.get_subscribers();
+
+            // clear the local subscriber set which will be replenished by each subscriber if
+            // it calls the value method later
+            self.subscribers.clear();
+        }
+        Some((subs, changed, false))
+    }
+
+    fn merge_error(&mut self, error: LazySignalsError) {
+        // streams only carry values
+        trace!("-dropping error sent to stream: {}", error);
+    }
+
+    fn merge_subscribers(&mut self) {
+        for subscriber in self.next_subscribers.indices() {
+            self.subscribers.insert(subscriber, ());
+        }
+        self.next_subscribers.clear();
+    }
+
+    fn subscribe(&mut self, entity: Entity) {
+        self.next_subscribers.insert(entity, ());
+    }
+
+    fn unsubscribe(&mut self, entity: Entity) {
+        self.subscribers.remove(entity);
+        self.next_subscribers.remove(entity);
+    }
+}
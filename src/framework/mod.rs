@@ -1,23 +1,39 @@
-use std::{ any::TypeId, fmt::Debug, sync::Mutex };
+use std::{
+    any::{ Any, TypeId },
+    fmt::Debug,
+    future::Future,
+    ops::Deref,
+    sync::{ atomic::{ AtomicU64, Ordering }, mpsc::{ self, Receiver, Sender }, Arc, Mutex },
+};
 
 use bevy::{
     ecs::{
         component::{ ComponentId, ComponentInfo },
         storage::SparseSet,
-        system::BoxedSystem,
-        world::CommandQueue,
+        system::{ BoxedSystem, SystemId },
+        world::{ CommandQueue, EntityWorldMut },
     },
     prelude::*,
+    ptr::PtrMut,
     reflect::{ DynamicTuple, GetTypeRegistration, Tuple },
-    tasks::Task,
+    tasks::{ AsyncComputeTaskPool, Task },
+    utils::{ all_tuples, BoxedFuture, HashMap },
 };
 
 use thiserror::Error;
 
-use crate::LazySignalsObservable;
+use crate::{
+    handles::{ LazySignalsSource, Signal },
+    LazySignalsImmutable,
+    LazySignalsObservable,
+    LazySignalsState,
+    StaticStrRef,
+};
 
 pub mod bundles;
 pub mod lazy_immutable;
+pub mod lazy_stream;
+pub mod signal_stream;
 
 /// # Signals framework
 /// ## Types
@@ -28,12 +44,318 @@ pub struct LazySignalsResult<R: LazySignalsData> {
     pub error: Option<LazySignalsError>,
 }
 
+impl<R: LazySignalsData> LazySignalsResult<R> {
+    /// Convert into an `Option<Result<R, LazySignalsError>>`, where `None` means no value and no error.
+    pub fn into_option(self) -> Option<Result<R, LazySignalsError>> {
+        self.into()
+    }
+
+    /// Convert into a `Result<Option<R>, LazySignalsError>`, which is what a results args tuple holds.
+    pub fn into_result(self) -> Result<Option<R>, LazySignalsError> {
+        self.into()
+    }
+}
+
+impl<R: LazySignalsData> From<LazySignalsResult<R>> for Option<Result<R, LazySignalsError>> {
+    fn from(result: LazySignalsResult<R>) -> Self {
+        match result.error {
+            Some(error) => Some(Err(error)),
+            None => result.data.map(Ok),
+        }
+    }
+}
+
+impl<R: LazySignalsData> From<Option<Result<R, LazySignalsError>>> for LazySignalsResult<R> {
+    fn from(option: Option<Result<R, LazySignalsError>>) -> Self {
+        match option {
+            Some(Ok(data)) => LazySignalsResult { data: Some(data), error: None },
+            Some(Err(error)) => LazySignalsResult { data: None, error: Some(error) },
+            None => LazySignalsResult { data: None, error: None },
+        }
+    }
+}
+
+impl<R: LazySignalsData> From<LazySignalsResult<R>> for Result<Option<R>, LazySignalsError> {
+    fn from(result: LazySignalsResult<R>) -> Self {
+        match result.error {
+            Some(error) => Err(error),
+            None => Ok(result.data),
+        }
+    }
+}
+
+impl<R: LazySignalsData> From<Result<Option<R>, LazySignalsError>> for LazySignalsResult<R> {
+    fn from(result: Result<Option<R>, LazySignalsError>) -> Self {
+        match result {
+            Ok(data) => LazySignalsResult { data, error: None },
+            Err(error) => LazySignalsResult { data: None, error: Some(error) },
+        }
+    }
+}
+
+/// Type of a value copied into an args tuple, with a name for error messages.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ArgType {
+    pub type_id: TypeId,
+    pub type_name: &'static str,
+}
+
+impl ArgType {
+    pub fn of<T: 'static>() -> Self {
+        Self {
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+        }
+    }
+}
+
+/// The future that an `Action` runs, which resolves to the commands it wants applied or an error.
+pub type ActionFuture = BoxedFuture<'static, Result<CommandQueue, LazySignalsError>>;
+
+/// The result of one run of an `Action`, sent from its task when it completes.
+pub struct ActionCompletion {
+    pub action: Entity,
+    pub id: u64,
+    pub result: Result<CommandQueue, LazySignalsError>,
+}
+
+/// How many times an `Action` tries to run a task, and how long it waits in between.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ActionRetry {
+    /// Total number of runs, including the first one.
+    pub max_attempts: u32,
+
+    /// Seconds to wait before the first retry.
+    pub delay: f32,
+
+    /// Each retry waits this many times longer than the one before.
+    pub factor: f32,
+}
+
+impl ActionRetry {
+    /// Get the seconds to wait after the given attempt (starting from 1) fails.
+    pub fn backoff(&self, attempt: u32) -> f32 {
+        self.delay * self.factor.powi(attempt.saturating_sub(1) as i32)
+    }
+}
+
+impl Default for ActionRetry {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            delay: 1.0,
+            factor: 2.0,
+        }
+    }
+}
+
 /// Return type for an optional list of entities and some flags (changed, triggered).
 pub type MaybeFlaggedEntities = Option<(Vec<Entity>, bool, bool)>;
 
+/// Error raised by developer code, with a message and an optional reflected payload.
+#[derive(Reflect, Debug)]
+pub struct LazySignalsUserError {
+    pub message: String,
+    #[reflect(ignore)]
+    pub payload: Option<Box<dyn Reflect>>,
+}
+
+impl LazySignalsUserError {
+    /// Get a concrete copy of the payload, if there is one of type `P`.
+    pub fn payload<P: FromReflect>(&self) -> Option<P> {
+        self.payload.as_ref().and_then(|payload| P::from_reflect(payload.as_ref()))
+    }
+}
+
+impl Clone for LazySignalsUserError {
+    fn clone(&self) -> Self {
+        Self {
+            message: self.message.clone(),
+            payload: self.payload.as_ref().map(|payload| payload.clone_value()),
+        }
+    }
+}
+
+impl PartialEq for LazySignalsUserError {
+    fn eq(&self, other: &Self) -> bool {
+        self.message == other.message &&
+            (match (&self.payload, &other.payload) {
+                (Some(payload), Some(other)) =>
+                    payload.reflect_partial_eq(other.as_ref()).unwrap_or(false),
+                (None, None) => true,
+                _ => false,
+            })
+    }
+}
+
+/// Wrapper that stores signal data behind an `Arc`, so every reader shares one immutable allocation.
+/// Cloning (i.e. `get`, `copy_data`, etc.) only bumps the reference count. Two values are equal if
+/// they point to the same allocation, so sending a new `Shared` always counts as a change, without
+/// ever comparing the (possibly huge) contents.
+#[derive(Reflect)]
+#[reflect_value(PartialEq)]
+pub struct Shared<T: Send + Sync + TypePath + 'static>(pub Arc<T>);
+
+impl<T: Send + Sync + TypePath + 'static> Shared<T> {
+    pub fn new(data: T) -> Self {
+        Self(Arc::new(data))
+    }
+}
+
+impl<T: Send + Sync + TypePath + 'static> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<T: Send + Sync + TypePath + 'static> Deref for Shared<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Send + Sync + TypePath + 'static> From<T> for Shared<T> {
+    fn from(data: T) -> Self {
+        Self::new(data)
+    }
+}
+
+impl<T: Send + Sync + TypePath + 'static> PartialEq for Shared<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Reader handed to a tracked `Computed`. Every signal read through it is recorded, and whatever
+/// was read on the last run becomes the set of sources the `Computed` subscribes to.
+pub struct TrackingContext<'a> {
+    world: &'a World,
+    reads: Vec<Entity>,
+}
+
+impl<'a> TrackingContext<'a> {
+    pub fn new(world: &'a World) -> Self {
+        Self {
+            world,
+            reads: Vec::new(),
+        }
+    }
+
+    /// Get the current value of a `Signal` or `Computed` and make it a source.
+    pub fn get<T: LazySignalsData>(
+        &mut self,
+        immutable: impl LazySignalsSource<Option<T>>
+    ) -> Option<T> {
+        self.state::<T>(immutable.source_entity()).and_then(|state| state.get())
+    }
+
+    /// Get the current value or error of a `Signal` or `Computed` and make it a source.
+    pub fn result<T: LazySignalsData>(
+        &mut self,
+        immutable: impl LazySignalsSource<Option<T>>
+    ) -> Result<Option<T>, LazySignalsError> {
+        match self.state::<T>(immutable.source_entity()) {
+            Some(state) =>
+                match state.error() {
+                    Some(error) => Err(error),
+                    None => Ok(state.get()),
+                }
+            None => Err(LazySignalsError::NoSignalError),
+        }
+    }
+
+    /// Consume the context and return each entity that was read, in the order they were first read.
+    pub fn into_reads(self) -> Vec<Entity> {
+        self.reads
+    }
+
+    fn state<T: LazySignalsData>(&mut self, immutable: Entity) -> Option<&'a LazySignalsState<T>> {
+        let entity = self.world.get_entity(immutable)?;
+
+        // only something that can be subscribed to is a source
+        if entity.contains::<ImmutableState>() && !self.reads.contains(&immutable) {
+            self.reads.push(immutable);
+        }
+        entity.get::<LazySignalsState<T>>()
+    }
+}
+
 /// ## Enums
+/// What an `Action` does when it is triggered (or a source changes) while its task is still running.
+/// Modeled on the `RxJS` flattening operators.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ActionConcurrency {
+    /// Ignore the new run until the running task completes, i.e. `exhaustMap` (the default).
+    #[default]
+    Exhaust,
+
+    /// Drop (cancel) the running task and start a new one with the new args, i.e. `switchMap`.
+    Switch,
+
+    /// Remember the latest args and run again with them once the running task completes, i.e.
+    /// `concatMap` that only keeps the last value.
+    Queue,
+
+    /// Run up to this many tasks at the same time and ignore any more than that, i.e. `mergeMap`.
+    Merge(usize),
+}
+
+/// How the values of the sources are copied into the args tuple of a `Computed`, `Effect`, or `Action`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ArgsMode {
+    /// Each source is an `Option<T>` and any error becomes `None` (the default).
+    #[default]
+    Options,
+
+    /// Each source is a `Result<Option<T>, LazySignalsError>`, so "no value yet" and "errored" can be
+    /// told apart.
+    Results,
+}
+
+/// The status of a resource, i.e. the result of the async task of an `Action` (see
+/// `LazySignals.resource`).
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub enum AsyncStatus<R: LazySignalsData> {
+    /// The task has not started yet.
+    Idle,
+
+    /// The task is running. Any previous result is dropped.
+    Pending,
+
+    /// The task completed with a value.
+    Ready(R),
+
+    /// The task completed with an error.
+    Failed(LazySignalsError),
+}
+
+impl<R: LazySignalsData> AsyncStatus<R> {
+    /// Get the error of a failed task, if any.
+    pub fn error(&self) -> Option<&LazySignalsError> {
+        match self {
+            Self::Failed(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Is the task running?
+    pub fn is_pending(&self) -> bool {
+        matches!(self, Self::Pending)
+    }
+
+    /// Get the value of a completed task, if any.
+    pub fn ready(&self) -> Option<&R> {
+        match self {
+            Self::Ready(data) => Some(data),
+            _ => None,
+        }
+    }
+}
+
 /// Read error.
-#[derive(Error, Clone, Copy, PartialEq, Reflect, Debug)]
+#[derive(Error, Clone, PartialEq, Reflect, Debug)]
 pub enum LazySignalsError {
     /// An attempt was made to reference a `LazySignals` entity that does not exist.
     #[error["Signal does not exist"]]
@@ -46,6 +368,93 @@ pub enum LazySignalsError {
     /// An attempt was made to read a signal and something weird went wrong.
     #[error("Error reading signal {0:?}")]
     ReadError(Entity),
+
+    /// The sources of a `Computed`, `Effect`, or `Action` do not line up with its args tuple.
+    #[error("Invalid args: {0}")]
+    InvalidArgs(String),
+
+    /// A `Computed` would depend on itself, directly or through other `Computed`s. Holds the path of
+    /// the cycle, starting and ending with the rejected node.
+    #[error("Dependency cycle: {}", format_path(.0))]
+    Cycle(Vec<Entity>),
+
+    /// The task of an `Action` ran longer than its timeout (in seconds).
+    #[error("Timed out after {0} seconds")]
+    Timeout(f32),
+
+    /// Developer code (e.g. a `Computed` closure or a `send_error` command) reported an error.
+    #[error("{}", .0.message)]
+    UserError(LazySignalsUserError),
+}
+
+/// Format a path of entities for error messages, e.g. `1v1 -> 2v1 -> 1v1`.
+pub(crate) fn format_path(path: &[Entity]) -> String {
+    path.iter()
+        .map(|entity| entity.to_string())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+impl LazySignalsError {
+    /// Make a `UserError` with the given message.
+    pub fn user(message: impl Into<String>) -> Self {
+        Self::UserError(LazySignalsUserError {
+            message: message.into(),
+            payload: None,
+        })
+    }
+
+    /// Make a `UserError` with the given message and a reflected payload (e.g. a validation report).
+    pub fn user_with_payload(message: impl Into<String>, payload: impl Reflect) -> Self {
+        Self::UserError(LazySignalsUserError {
+            message: message.into(),
+            payload: Some(Box::new(payload)),
+        })
+    }
+}
+
+/// Change detection policy that decides whether a merged value is different from the current one.
+#[derive(Default)]
+pub enum LazySignalsEquality<T: LazySignalsData> {
+    /// Every merged value is treated as a change, even if it is equal to the current one.
+    Always,
+
+    /// Compare values with `PartialEq` (the default).
+    #[default]
+    PartialEq,
+
+    /// Compare values with a closure that returns `true` when they should be considered equal.
+    Custom(Arc<dyn EqualityFn<T>>),
+}
+
+impl<T: LazySignalsData> LazySignalsEquality<T> {
+    /// Compare values with a closure that returns `true` when they should be considered equal.
+    pub fn custom(equals: impl EqualityFn<T>) -> Self {
+        Self::Custom(Arc::new(equals))
+    }
+
+    /// Compare a key (e.g. a version number or an ID) instead of the whole value.
+    pub fn by_key<K: PartialEq>(key: impl Send + Sync + 'static + Fn(&T) -> K) -> Self {
+        Self::custom(move |data, next| key(data) == key(next))
+    }
+
+    /// Treat floating point values within `epsilon` of each other as equal (e.g. to ignore jitter).
+    pub fn epsilon(epsilon: f64) -> Self where T: Copy + Into<f64> {
+        Self::custom(move |data: &T, next: &T| {
+            let data: f64 = (*data).into();
+            let next: f64 = (*next).into();
+            (data - next).abs() <= epsilon
+        })
+    }
+
+    /// Returns `true` if the values should be considered equal (i.e. the value did not change).
+    pub fn equals(&self, data: &T, next: &T) -> bool {
+        match self {
+            Self::Always => false,
+            Self::PartialEq => data == next,
+            Self::Custom(equals) => equals(data, next),
+        }
+    }
 }
 
 // ## Traits
@@ -71,8 +480,40 @@ impl<T> LazySignalsData
             'static {}
 
 /// A tuple containing parameters for a computed memo or effect.
-pub trait LazySignalsArgs: LazySignalsData + Tuple {}
-impl<T> LazySignalsArgs for T where T: LazySignalsData + Tuple {}
+pub trait LazySignalsArgs: LazySignalsData + Tuple + TakeArgs {}
+impl<T> LazySignalsArgs for T where T: LazySignalsData + Tuple + TakeArgs {}
+
+/// Build a concrete args tuple by moving each value out of the `DynamicTuple` filled in by the
+/// sources, so a value is only copied once per run (by the `Clone` fast path, if it has one).
+pub trait TakeArgs: Sized {
+    fn take_args(tuple: DynamicTuple) -> Option<Self>;
+}
+
+macro_rules! impl_take_args {
+    ($($A:ident),*) => {
+        impl<$($A: FromReflect),*> TakeArgs for ($($A,)*) {
+            #[allow(unused_mut, unused_variables)]
+            fn take_args(tuple: DynamicTuple) -> Option<Self> {
+                let mut fields = Box::new(tuple).drain().into_iter();
+                Some(($(take_field::<$A>(fields.next()?)?,)*))
+            }
+        }
+    };
+}
+
+all_tuples!(impl_take_args, 0, 12, A);
+
+// a source inserts its value as the concrete type, so it can be moved out as is
+fn take_field<A: FromReflect>(field: Box<dyn Reflect>) -> Option<A> {
+    match field.take::<A>() {
+        Ok(value) => Some(value),
+        Err(field) => A::from_reflect(&*field),
+    }
+}
+
+/// Comparator for a `LazySignalsEquality` policy. Returns `true` if the values are considered equal.
+pub trait EqualityFn<T: LazySignalsData>: Send + Sync + 'static + Fn(&T, &T) -> bool {}
+impl<T: LazySignalsData, F: Send + Sync + 'static + Fn(&T, &T) -> bool> EqualityFn<T> for F {}
 
 /// A propagator function aggregates (merges) data from multiple cells to store in a bound cell.
 /// Compared to the MIT model, the `Computed` pulls data into a cell they are bound to.
@@ -81,12 +522,29 @@ impl<T> LazySignalsArgs for T where T: LazySignalsData + Tuple {}
 ///
 /// The `DynamicTuple` is an argument list whose internal types match the `Option<T>` of each source.
 /// (i.e. `SignalsResult` with no error and some data becomes `Option<T>` with any `Err` becoming `None`)
+/// With `ArgsMode::Results`, each internal type is a `Result<Option<T>, LazySignalsError>` instead.
+///
+/// The `LazySignalsError` is the first error found in the sources, if any, so a `Computed` can choose
+/// to pass it through instead of running.
+///
+/// It only reads the world, so `Computed`s of the same rank can run in parallel. What it returns is
+/// a `ComputedStore` that writes the result afterwards.
+pub trait ComputedContext: Send +
+    Sync +
+    FnMut(DynamicTuple, Option<LazySignalsError>, &World) -> Box<dyn ComputedStore> {}
+impl<
+    T: Send +
+        Sync +
+        FnMut(DynamicTuple, Option<LazySignalsError>, &World) -> Box<dyn ComputedStore>
+> ComputedContext for T {}
+
+/// Stores the result of a `ComputedContext` and returns `true` if the value changed.
 ///
 /// The entity is where the result will be stored, where this instance of the function lives.
 ///
 /// The world is the world is love and life are deep.
-pub trait ComputedContext: Send + Sync + FnMut(&DynamicTuple, &Entity, &mut World) -> bool {}
-impl<T: Send + Sync + FnMut(&DynamicTuple, &Entity, &mut World) -> bool> ComputedContext for T {}
+pub trait ComputedStore: Send + FnOnce(&Entity, &mut World) -> bool {}
+impl<T: Send + FnOnce(&Entity, &mut World) -> bool> ComputedStore for T {}
 
 /// Let the developer pass in a regular Rust closure that borrows a concrete typed tuple as args.
 /// The return type is a `LazySignalsResult` which can then be memoized.
@@ -100,9 +558,20 @@ impl<
     T: Send + Sync + 'static + Fn(P) -> LazySignalsResult<R>
 > Computed<P, R> for T {}
 
+/// Let the developer pass in a regular Rust closure that reads its sources through a
+/// `TrackingContext` instead of getting them as args.
+pub trait TrackedComputed<R: LazySignalsData>: Send +
+    Sync +
+    'static +
+    Fn(&mut TrackingContext) -> LazySignalsResult<R> {}
+impl<
+    R: LazySignalsData,
+    T: Send + Sync + 'static + Fn(&mut TrackingContext) -> LazySignalsResult<R>
+> TrackedComputed<R> for T {}
+
 /// This is the same basic thing but this fn just runs side-effects so no value is returned.
-pub trait EffectWrapper: Send + Sync + FnMut(&DynamicTuple, &mut World) -> Option<BoxedSystem> {}
-impl<T: Send + Sync + FnMut(&DynamicTuple, &mut World) -> Option<BoxedSystem>> EffectWrapper
+pub trait EffectWrapper: Send + Sync + FnMut(DynamicTuple, &mut World) -> Option<BoxedSystem> {}
+impl<T: Send + Sync + FnMut(DynamicTuple, &mut World) -> Option<BoxedSystem>> EffectWrapper
 for T {}
 
 /// Let the developer pass in a regular Rust closure that borrows a concrete typed tuple as args.
@@ -115,17 +584,80 @@ impl<
     T: Send + Sync + 'static + FnMut(P, &mut World) -> Option<BoxedSystem>
 > Effect<P> for T {}
 
-pub trait ActionWrapper: Send + Sync + Fn(&DynamicTuple) -> Task<CommandQueue> {}
-impl<T: Send + Sync + Fn(&DynamicTuple) -> Task<CommandQueue>> ActionWrapper for T {}
+pub trait ActionWrapper: Send + Sync + Fn(&DynamicTuple) -> ActionFuture {}
+impl<T: Send + Sync + Fn(&DynamicTuple) -> ActionFuture> ActionWrapper for T {}
 
 pub trait Action<P: LazySignalsArgs>: Send + Sync + 'static + Fn(P) -> Task<CommandQueue> {}
 impl<P: LazySignalsArgs, T: Send + Sync + 'static + Fn(P) -> Task<CommandQueue>> Action<P> for T {}
 
+/// Let the developer pass in a closure that spawns a task which can fail, e.g. so it is retried.
+pub trait FallibleAction<P: LazySignalsArgs>: Send +
+    Sync +
+    'static +
+    Fn(P) -> Task<Result<CommandQueue, LazySignalsError>> {}
+impl<
+    P: LazySignalsArgs,
+    T: Send + Sync + 'static + Fn(P) -> Task<Result<CommandQueue, LazySignalsError>>
+> FallibleAction<P> for T {}
+
+/// Let the developer pass in an async closure (or any closure that returns a `Future`) that takes
+/// a concrete typed tuple as args and resolves to the data of a resource.
+pub trait AsyncResource<P: LazySignalsArgs, R: LazySignalsData>: Send + Sync + 'static {
+    type Future: Future<Output = Result<R, LazySignalsError>> + Send + 'static;
+
+    fn fetch(&self, args: P) -> Self::Future;
+}
+impl<
+    P: LazySignalsArgs,
+    R: LazySignalsData,
+    F: Future<Output = Result<R, LazySignalsError>> + Send + 'static,
+    T: Send + Sync + 'static + Fn(P) -> F
+> AsyncResource<P, R> for T {
+    type Future = F;
+
+    fn fetch(&self, args: P) -> F {
+        self(args)
+    }
+}
+
 pub enum EffectContext {
     Short(Mutex<Box<dyn EffectWrapper>>),
     Long(Mutex<Box<dyn ActionWrapper>>),
+
+    /// A one-shot system registered when the `Effect` is created. It takes the args tuple as a
+    /// `DynamicTuple` (converted to the concrete `In<P>` by a wrapping system) and keeps its state,
+    /// e.g. `Local`s and change ticks, between runs.
+    System(SystemId<DynamicTuple>),
 }
 
+/// Queued update that derives the next value of a state from the current (or pending) one.
+/// Returning `None` leaves the value as it is.
+pub trait UpdaterWrapper<T: LazySignalsData>: Send +
+    Sync +
+    FnOnce(Option<&T>) -> Option<T> {}
+impl<T: LazySignalsData, F: Send + Sync + FnOnce(Option<&T>) -> Option<T>> UpdaterWrapper<T>
+for F {}
+
+/// Let the developer pass in a regular Rust closure that derives the next value from the current one.
+pub trait Updater<T: LazySignalsData>: Send + Sync + 'static + FnOnce(Option<&T>) -> T {}
+impl<T: LazySignalsData, F: Send + Sync + 'static + FnOnce(Option<&T>) -> T> Updater<T> for F {}
+
+/// Let the developer pass in a regular Rust closure that folds a message into the current state.
+pub trait Reducer<S: LazySignalsData, M: Send + Sync + 'static>: Send +
+    Sync +
+    'static +
+    Fn(&S, M) -> S {}
+impl<
+    S: LazySignalsData,
+    M: Send + Sync + 'static,
+    F: Send + Sync + 'static + Fn(&S, M) -> S
+> Reducer<S, M> for F {}
+
+/// Type-erased dispatcher that queues a message to be reduced into the state of the given entity.
+pub trait ReducerWrapper<M: Send + Sync + 'static>: Send + Sync + Fn(M, &mut EntityWorldMut) {}
+impl<M: Send + Sync + 'static, F: Send + Sync + Fn(M, &mut EntityWorldMut)> ReducerWrapper<M>
+for F {}
+
 /// Catch-all fn signature for `LazySignalsObservable` operations.
 pub trait ObservableFn: Send +
     Sync +
@@ -147,9 +679,61 @@ impl<
 /// ## Component Structs
 ///
 /// An `ImmutableState` stores the `ComponentId` of a `LazySignalsState<T>` with concrete `T`.
+/// It also stores the type of each arg the cell provides, so sources can be checked against the args
+/// tuple of a `Computed`, `Effect`, or `Action` when it is created.
 #[derive(Component)]
 pub struct ImmutableState {
     pub component_id: ComponentId,
+    pub arg_type: ArgType,
+    pub result_arg_type: ArgType,
+}
+
+impl ImmutableState {
+    /// Make the `ImmutableState` for a `LazySignalsState<T>` (`Option<T>` arg).
+    pub fn for_state<T: LazySignalsData>(component_id: ComponentId) -> Self {
+        Self {
+            component_id,
+            arg_type: ArgType::of::<Option<T>>(),
+            result_arg_type: ArgType::of::<Result<Option<T>, LazySignalsError>>(),
+        }
+    }
+
+    /// Make the `ImmutableState` for a `LazySignalsStream<T>` (`Vec<T>` arg).
+    pub fn for_stream<T: LazySignalsData>(component_id: ComponentId) -> Self {
+        Self {
+            component_id,
+            arg_type: ArgType::of::<Vec<T>>(),
+            result_arg_type: ArgType::of::<Result<Vec<T>, LazySignalsError>>(),
+        }
+    }
+
+    /// Get the type of the arg this cell copies into an args tuple of the given mode.
+    pub fn arg_type(&self, args_mode: ArgsMode) -> ArgType {
+        match args_mode {
+            ArgsMode::Options => self.arg_type,
+            ArgsMode::Results => self.result_arg_type,
+        }
+    }
+}
+
+/// A `Faulted` component marks a `Computed`, `Effect`, or `Action` that can not run, e.g. because its
+/// sources do not line up with its args.
+#[derive(Component)]
+pub struct Faulted {
+    pub error: LazySignalsError,
+}
+
+/// An `OwnedBySources` component marks a `Computed`, `Effect`, or `Action` that should be despawned
+/// along with any of its sources or triggers, instead of being marked with `NoSignalError`.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct OwnedBySources;
+
+/// An `OwnedByEffect` component marks the entity of a one-shot system registered for an `Effect`,
+/// so the system can be removed when the `Effect` is despawned.
+#[derive(Component)]
+pub struct OwnedByEffect {
+    pub effect: Entity,
 }
 
 /// A `SendSignal` component marks a `LazySignalsState` cell as having a `next_value`.
@@ -162,6 +746,7 @@ pub struct SendSignal;
 pub struct ComputedImmutable {
     pub function: Mutex<Box<dyn ComputedContext>>,
     pub sources: Vec<Entity>,
+    pub args_mode: ArgsMode,
     pub args_type: TypeId,
     pub result_type: TypeId,
 }
@@ -171,15 +756,37 @@ pub struct ComputedImmutable {
 #[component(storage = "SparseSet")]
 pub struct ComputeMemo;
 
+/// A `ForceCompute` component marks a `Computed` that runs the next time it is marked with
+/// `ComputeMemo` even if none of its sources changed, i.e. it is new or was re-wired.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct ForceCompute;
+
 /// A `LazyEffect` returns no value and just runs side-effects.
 #[derive(Component)]
 pub struct LazyEffect {
     pub function: EffectContext,
     pub sources: Vec<Entity>,
     pub triggers: Vec<Entity>,
+    pub args_mode: ArgsMode,
     pub args_type: TypeId,
 }
 
+/// `ActionOptions` configure how an `Action` runs. An `Action` without them uses the defaults.
+#[derive(Component, Clone, Debug, Default)]
+pub struct ActionOptions {
+    pub concurrency: ActionConcurrency,
+
+    /// Cancel a task that runs longer than this many seconds of `Time`, which counts as a failure.
+    pub timeout: Option<f32>,
+
+    /// Run a task again (with the same args) after it fails.
+    pub retry: Option<ActionRetry>,
+
+    /// Send the error here when a task fails for good, i.e. after the last retry.
+    pub failures: Option<Signal<LazySignalsError>>,
+}
+
 /// A DeferredEffect component marks an Effect function that needs to run.
 #[derive(Component)]
 #[component(storage = "SparseSet")]
@@ -196,15 +803,127 @@ pub struct Dirty;
 #[component(storage = "SparseSet")]
 pub struct InitDependencies;
 
+/// A `LazyReducer` dispatches messages of type `M` into the `LazySignalsState` on the same entity.
+/// Every message dispatched in a tick is folded through the reducer, in order, when the state is sent.
+#[derive(Component)]
+pub struct LazyReducer<M: Send + Sync + 'static> {
+    pub function: Arc<dyn ReducerWrapper<M>>,
+}
+
+/// A `LazyResource` component marks an `Action` that writes the result of its task into the
+/// `LazySignalsState<AsyncStatus<R>>` on the same entity. The framework calls these to mark the
+/// status when a task starts (`pending`), fails for good (`failed`), or is cancelled (`idle`).
+#[derive(Component)]
+pub struct LazyResource {
+    pub idle: fn(Entity, &mut World),
+    pub pending: fn(Entity, &mut World),
+    pub failed: fn(Entity, LazySignalsError, &mut World),
+}
+
+impl LazyResource {
+    pub fn new<R: LazySignalsData>() -> Self {
+        Self {
+            idle: |entity, world| send_status::<R>(entity, AsyncStatus::Idle, world),
+            pending: |entity, world| send_status::<R>(entity, AsyncStatus::Pending, world),
+            failed: |entity, error, world| {
+                send_status::<R>(entity, AsyncStatus::Failed(error), world);
+            },
+        }
+    }
+}
+
+fn send_status<R: LazySignalsData>(entity: Entity, status: AsyncStatus<R>, world: &mut World) {
+    let Some(mut entity) = world.get_entity_mut(entity) else {
+        return;
+    };
+    if let Some(mut state) = entity.get_mut::<LazySignalsState<AsyncStatus<R>>>() {
+        state.merge_next(LazySignalsResult { data: Some(status), error: None }, false);
+        entity.insert(SendSignal);
+    }
+}
+
+/// A `LazyStream` component marks the entity of a `LazySignalsStream`, so its values can be drained
+/// once per tick no matter how many times the batch is flushed.
+#[derive(Component)]
+pub struct LazyStream;
+
+/// A `Rank` caches the height of a `Computed`, `Effect`, or `Action` in the dependency graph: one
+/// more than its highest source, where a state is 0. Dirty memos are computed in rank order, so each
+/// one only runs after all of its sources are up to date.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Rank {
+    pub height: u32,
+}
+
 /// A `RunningTask` component marks an `Effect` function that may still be running.
+/// It holds every task that is in flight (or waiting to be retried), plus the args for the next run
+/// if one is queued.
 #[derive(Component)]
 #[component(storage = "SparseSet")]
 pub struct RunningTask {
-    pub task: Task<CommandQueue>,
+    pub tasks: Vec<ActionTask>,
+    pub queued: Option<DynamicTuple>,
+}
+
+impl RunningTask {
+    pub fn new(task: ActionTask) -> Self {
+        Self {
+            tasks: vec![task],
+            queued: None,
+        }
+    }
+
+    /// Whether any of the tasks has to be checked against the clock, i.e. it can time out or it is
+    /// waiting to be retried.
+    pub fn has_deadline(&self, options: Option<&ActionOptions>) -> bool {
+        let timeout = options.is_some_and(|options| options.timeout.is_some());
+        self.tasks.iter().any(|task| timeout || task.task.is_none())
+    }
 }
 
+/// A `TaskDeadline` component marks an `Action` with a task that can time out or is waiting to be
+/// retried, so only those are checked against the clock each tick.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct TaskDeadline;
+
+/// One run of an `Action`, kept with its args so it can be retried.
+pub struct ActionTask {
+    /// Identifies the completion of this run (see `LazySignalsCompletions`).
+    pub id: u64,
+
+    /// `None` while waiting to be retried. Dropping the task cancels it.
+    pub task: Option<Task<()>>,
+    pub args: DynamicTuple,
+
+    /// Starts from 1.
+    pub attempt: u32,
+
+    /// The elapsed `Time` in seconds when the task started, or when it will be retried.
+    pub start: f32,
+}
+
+impl ActionTask {
+    pub fn new(id: u64, task: Task<()>, args: DynamicTuple, start: f32) -> Self {
+        Self {
+            id,
+            task: Some(task),
+            args,
+            attempt: 1,
+            start,
+        }
+    }
+}
+
+/// A `TrackedSources` component marks a `Computed` whose sources are whatever it read on its last
+/// run, instead of a fixed list that lines up with an args tuple.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct TrackedSources;
+
 /// A `Triggered` component marks a `Computed` triggers any effect anywhere down its subscriber tree.
-/// It also marks any `Effect` that has been triggered this way.
+/// It also marks any `Effect` that has been triggered this way, and any `Signal` that was sent with
+/// a trigger during the last batch.
 #[derive(Component)]
 #[component(storage = "SparseSet")]
 pub struct Triggered;
@@ -214,6 +933,268 @@ pub struct Triggered;
 #[component(storage = "SparseSet")]
 pub struct ValueChanged;
 
+/// ## Resources
+/// Per-frame limits that keep a feedback loop (e.g. an `Effect` that sends one of its own sources)
+/// from stalling the app. Hitting a limit is logged and sent as a `LazySignalsLoopDetected` event.
+#[derive(Resource, Clone, Debug)]
+pub struct LazySignalsConfig {
+    /// Maximum number of times signals are passed on to the next level of subscribers per frame.
+    pub max_propagation_rounds: usize,
+
+    /// Maximum number of times each `Effect` or `Action` can run per frame, or in a row while it
+    /// keeps sending its own sources (so it runs again the next time effects are applied).
+    pub max_effect_runs: usize,
+
+    /// Stop propagating (or skip the `Effect`) once a limit is hit, instead of only reporting it.
+    pub cut_off: bool,
+}
+
+impl Default for LazySignalsConfig {
+    fn default() -> Self {
+        Self {
+            max_propagation_rounds: 1024,
+            max_effect_runs: 16,
+            cut_off: false,
+        }
+    }
+}
+
+/// The tasks of every `Action` report here when they complete, so only the tasks that actually
+/// completed are looked at each tick instead of polling every one that is in flight.
+#[derive(Resource)]
+pub struct LazySignalsCompletions {
+    next_id: AtomicU64,
+    sender: Sender<ActionCompletion>,
+    receiver: Mutex<Receiver<ActionCompletion>>,
+}
+
+impl Default for LazySignalsCompletions {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            next_id: AtomicU64::new(0),
+            sender,
+            receiver: Mutex::new(receiver),
+        }
+    }
+}
+
+impl LazySignalsCompletions {
+    /// Take every completion that has been reported so far.
+    pub fn drain(&self) -> Vec<ActionCompletion> {
+        self.receiver.lock().unwrap().try_iter().collect()
+    }
+
+    /// Run the future of an `Action` on the `AsyncComputeTaskPool` and report here when it
+    /// completes. Returns the ID of the completion along with the task.
+    pub fn spawn(&self, action: Entity, future: ActionFuture) -> (u64, Task<()>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let sender = self.sender.clone();
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            let result = future.await;
+
+            // the receiving end only goes away with the app
+            sender.send(ActionCompletion { action, id, result }).ok();
+        });
+        (id, task)
+    }
+}
+
+/// Work done during the current frame, counted against the limits in `LazySignalsConfig`.
+#[derive(Resource)]
+pub struct LazySignalsBudget {
+    frame: Option<u32>,
+    drained: Option<u32>,
+    pass: u64,
+    pub propagation_rounds: usize,
+    pub effect_runs: SparseSet<Entity, usize>,
+
+    /// The last pass in which each `Effect` sent one of its own sources, and how many passes in a
+    /// row it has done so. Unlike the other counts, this one carries over between frames.
+    pub self_sends: SparseSet<Entity, (u64, usize)>,
+}
+
+impl Default for LazySignalsBudget {
+    fn default() -> Self {
+        Self {
+            frame: None,
+            drained: None,
+            pass: 0,
+            propagation_rounds: 0,
+            effect_runs: SparseSet::new(),
+            self_sends: SparseSet::new(),
+        }
+    }
+}
+
+impl LazySignalsBudget {
+    /// Start counting from zero when a new frame begins. Without a `FrameCount` (i.e. no
+    /// `FrameCountPlugin`) each system run gets a fresh budget.
+    pub fn start_frame(&mut self, frame: Option<u32>) {
+        if frame.is_none() || frame != self.frame {
+            self.frame = frame;
+            self.propagation_rounds = 0;
+            self.effect_runs.clear();
+        }
+    }
+
+    /// Returns `true` the first time it is called during a frame, i.e. when the streams still hold
+    /// the values delivered during an earlier one. Without a `FrameCount` it is always `true`.
+    pub fn start_stream_frame(&mut self, frame: Option<u32>) -> bool {
+        let stale = frame.is_none() || frame != self.drained;
+        self.drained = frame;
+        stale
+    }
+
+    /// Count one more propagation round this frame and return the total.
+    pub fn count_propagation_round(&mut self) -> usize {
+        self.propagation_rounds += 1;
+        self.propagation_rounds
+    }
+
+    /// Count one more run of the given `Effect` this frame and return the total.
+    pub fn count_effect_run(&mut self, effect: Entity) -> usize {
+        let runs = self.effect_runs.get(effect).copied().unwrap_or(0) + 1;
+        self.effect_runs.insert(effect, runs);
+        runs
+    }
+
+    /// Count one more run of the given `Effect` that sent one of its own sources and return how
+    /// many passes in a row it has done so, including this one.
+    pub fn count_self_send(&mut self, effect: Entity) -> usize {
+        let streak = self.self_send_streak(effect) + 1;
+        self.self_sends.insert(effect, (self.pass, streak));
+        streak
+    }
+
+    /// Start a new pass of applying effects.
+    pub fn next_pass(&mut self) {
+        self.pass += 1;
+    }
+
+    /// How many passes in a row, up to the previous one, the given `Effect` sent its own sources.
+    pub fn self_send_streak(&self, effect: Entity) -> usize {
+        match self.self_sends.get(effect) {
+            Some((pass, streak)) if pass + 1 == self.pass => *streak,
+            _ => 0,
+        }
+    }
+}
+
+/// Monomorphized entry point for one concrete `LazySignalsObservable` component type. Every trait
+/// method (merge, copy_data, subscribe, get_subscribers, etc.) is then called through the vtable the
+/// compiler made for that type, instead of through the `TypeRegistry`.
+#[derive(Clone, Copy)]
+pub struct LazySignalsVTable {
+    /// Cast a pointer to the component into a trait object.
+    ///
+    /// safety: the pointer must point to the component type the vtable was made for
+    pub as_observable: for<'a> unsafe fn(PtrMut<'a>) -> &'a mut dyn LazySignalsObservable,
+}
+
+impl LazySignalsVTable {
+    pub fn of<T: LazySignalsObservable + 'static>() -> Self {
+        Self {
+            as_observable: as_observable::<T>,
+        }
+    }
+}
+
+unsafe fn as_observable<T: LazySignalsObservable + 'static>(
+    ptr: PtrMut<'_>
+) -> &'_ mut dyn LazySignalsObservable {
+    ptr.deref_mut::<T>()
+}
+
+/// Table of `LazySignalsVTable` by `ComponentId`, filled in by the create commands the first time
+/// they see each concrete type, so the systems do not need to take the `TypeRegistry` lock.
+///
+/// It also keeps a direct `Clone` fn for each data type that registered one, which is handed to
+/// every `LazySignalsState` of that type created afterwards (the primitive types, `String`, and the
+/// convenience types come built in).
+#[derive(Resource)]
+pub struct LazySignalsRegistry {
+    vtables: SparseSet<ComponentId, LazySignalsVTable>,
+    cloners: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Default for LazySignalsRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            vtables: SparseSet::new(),
+            cloners: HashMap::new(),
+        };
+        registry.register_clone::<bool>();
+        registry.register_clone::<char>();
+        registry.register_clone::<f32>();
+        registry.register_clone::<f64>();
+        registry.register_clone::<i8>();
+        registry.register_clone::<i16>();
+        registry.register_clone::<i32>();
+        registry.register_clone::<i64>();
+        registry.register_clone::<isize>();
+        registry.register_clone::<u8>();
+        registry.register_clone::<u16>();
+        registry.register_clone::<u32>();
+        registry.register_clone::<u64>();
+        registry.register_clone::<usize>();
+        registry.register_clone::<String>();
+        registry.register_clone::<StaticStrRef>();
+        registry.register_clone::<()>();
+        registry
+    }
+}
+
+impl LazySignalsRegistry {
+    /// Get the direct `Clone` fn for `T`, if it was registered.
+    pub fn cloner<T: LazySignalsData>(&self) -> Option<fn(&T) -> T> {
+        self.cloners
+            .get(&TypeId::of::<T>())
+            .and_then(|cloner| cloner.downcast_ref::<fn(&T) -> T>())
+            .copied()
+    }
+
+    /// Get the vtable for the component type with the given `ComponentId`, if it was registered.
+    pub fn get(&self, component_id: ComponentId) -> Option<&LazySignalsVTable> {
+        self.vtables.get(component_id)
+    }
+
+    /// Register the vtable of `T` if this is the first time it shows up.
+    pub fn register<T: LazySignalsObservable + 'static>(&mut self, component_id: ComponentId) {
+        if !self.vtables.contains(component_id) {
+            self.vtables.insert(component_id, LazySignalsVTable::of::<T>());
+        }
+    }
+
+    /// Let a `Clone` type skip the `FromReflect` round trip every time its value is copied.
+    pub fn register_clone<T: LazySignalsData + Clone>(&mut self) {
+        let cloner: fn(&T) -> T = T::clone;
+        self.cloners.insert(TypeId::of::<T>(), Box::new(cloner));
+    }
+}
+
+/// ## Events
+/// Which `LazySignalsConfig` limit was hit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LazySignalsLimit {
+    PropagationRounds,
+    EffectRuns,
+}
+
+/// Sent when a `LazySignalsConfig` limit is hit.
+#[derive(Event, Clone, Debug)]
+pub struct LazySignalsLoopDetected {
+    pub limit: LazySignalsLimit,
+
+    /// The entities involved, ending with the one that hit the limit. For `PropagationRounds` this
+    /// is the path from the signal that started it, for `EffectRuns` it is the sources and triggers
+    /// that made the `Effect` run again.
+    pub chain: Vec<Entity>,
+
+    /// Whether the loop was cut off (see `LazySignalsConfig::cut_off`).
+    pub cut_off: bool,
+}
+
 /// ## Utilities
 /// Set of `Entity` to `ComponentId`.
 pub type ComponentIdSet = SparseSet<Entity, ComponentId>;
@@ -234,3 +1215,127 @@ pub type ErrorSet = SparseSet<Entity, LazySignalsError>;
 pub fn empty_set() -> EntitySet {
     EntitySet::new()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use bevy::{ reflect::ReflectRef, tasks::{ block_on, TaskPool } };
+
+    use super::*;
+
+    thread_local! {
+        static FROM_REFLECT_CALLS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    // counts how many times it is rebuilt through reflection
+    #[derive(Reflect, PartialEq, Debug)]
+    #[reflect(from_reflect = false)]
+    struct Payload(u32);
+
+    impl FromReflect for Payload {
+        fn from_reflect(reflect: &dyn Reflect) -> Option<Self> {
+            FROM_REFLECT_CALLS.with(|calls| calls.set(calls.get() + 1));
+            let ReflectRef::TupleStruct(payload) = reflect.reflect_ref() else {
+                return None;
+            };
+            payload.field(0)?.downcast_ref::<u32>().map(|value| Payload(*value))
+        }
+    }
+
+    fn args() -> DynamicTuple {
+        let mut args = DynamicTuple::default();
+        args.insert(Some(Payload(1)));
+        args.insert(Option::<u32>::None);
+        args
+    }
+
+    #[test]
+    fn backoff_grows_by_the_factor() {
+        let retry = ActionRetry { max_attempts: 4, delay: 0.5, factor: 2.0 };
+        assert_eq!(retry.backoff(1), 0.5);
+        assert_eq!(retry.backoff(2), 1.0);
+        assert_eq!(retry.backoff(3), 2.0);
+
+        // a constant delay
+        let retry = ActionRetry { factor: 1.0, ..retry };
+        assert_eq!(retry.backoff(3), 0.5);
+    }
+
+    #[test]
+    fn equality_defaults_to_partial_eq() {
+        let equality = LazySignalsEquality::<u32>::default();
+        assert!(equality.equals(&1, &1));
+        assert!(!equality.equals(&1, &2));
+    }
+
+    #[test]
+    fn always_is_never_equal() {
+        assert!(!LazySignalsEquality::<u32>::Always.equals(&1, &1));
+    }
+
+    #[test]
+    fn by_key_compares_keys_only() {
+        let equality = LazySignalsEquality::<(u32, u32)>::by_key(|(version, _)| *version);
+        assert!(equality.equals(&(1, 10), &(1, 20)));
+        assert!(!equality.equals(&(1, 10), &(2, 10)));
+    }
+
+    #[test]
+    fn epsilon_ignores_jitter() {
+        let equality = LazySignalsEquality::<f32>::epsilon(0.01);
+        assert!(equality.equals(&1.0, &1.005));
+        assert!(!equality.equals(&1.0, &1.02));
+    }
+
+    #[test]
+    fn take_args_moves_concrete_values() {
+        FROM_REFLECT_CALLS.with(|calls| calls.set(0));
+        let args = <(Option<Payload>, Option<u32>)>::take_args(args());
+        assert_eq!(args, Some((Some(Payload(1)), None)));
+        assert_eq!(FROM_REFLECT_CALLS.with(|calls| calls.get()), 0);
+    }
+
+    #[test]
+    fn take_args_falls_back_to_from_reflect() {
+        FROM_REFLECT_CALLS.with(|calls| calls.set(0));
+        let mut dynamic = DynamicTuple::default();
+        dynamic.insert_boxed(args().field(0).unwrap().clone_value());
+        dynamic.insert(Option::<u32>::None);
+        let args = <(Option<Payload>, Option<u32>)>::take_args(dynamic);
+        assert_eq!(args, Some((Some(Payload(1)), None)));
+        assert_eq!(FROM_REFLECT_CALLS.with(|calls| calls.get()), 1);
+    }
+
+    #[test]
+    fn take_args_rejects_missing_args() {
+        assert_eq!(<(Option<u32>, Option<u32>)>::take_args(DynamicTuple::default()), None);
+    }
+
+    #[test]
+    fn completions_report_their_action_and_id() {
+        AsyncComputeTaskPool::get_or_init(TaskPool::default);
+        let completions = LazySignalsCompletions::default();
+        let (first, second) = (Entity::from_raw(1), Entity::from_raw(2));
+        let (first_id, first_task) = completions.spawn(
+            first,
+            Box::pin(async { Ok(CommandQueue::default()) })
+        );
+        let (second_id, second_task) = completions.spawn(
+            second,
+            Box::pin(async { Err(LazySignalsError::user("failed")) })
+        );
+        assert_ne!(first_id, second_id);
+        block_on(first_task);
+        block_on(second_task);
+
+        let mut reported = completions
+            .drain()
+            .into_iter()
+            .map(|completion| (completion.action, completion.id, completion.result.is_ok()))
+            .collect::<Vec<_>>();
+        reported.sort_by_key(|(_, id, _)| *id);
+        assert_eq!(reported, vec![(first, first_id, true), (second, second_id, false)]);
+        assert!(completions.drain().is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+use std::{ pin::Pin, sync::{ Arc, Mutex }, task::{ Context, Poll, Waker } };
+
+use bevy::tasks::futures_lite::{ Stream, StreamExt };
+
+use super::*;
+
+/// The slot shared by one `SignalStream` and the `SignalWatcher` that feeds it.
+struct WatchSlot<T> {
+    /// The latest value that has not been seen yet. Only the latest one is kept.
+    next: Option<Option<T>>,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+/// A `SignalStream` is an async `Stream` of the later values of a `Signal` or `Computed`, in the
+/// style of `futures-signals`. It is fed by the `LazySignals` systems each time the value changes,
+/// so an `Action` task can `.await` a change instead of being spawned again for every trigger.
+///
+/// Like a `watch` channel it is lossy: if the value changes more than once before the stream is
+/// polled, only the latest one is delivered. An error is delivered as `None`, like `read`. The
+/// stream ends when the entity is despawned.
+pub struct SignalStream<T: LazySignalsData> {
+    slot: Arc<Mutex<WatchSlot<T>>>,
+}
+
+impl<T: LazySignalsData> SignalStream<T> {
+    /// Make a stream and the `SignalWatcher` that feeds it.
+    pub fn channel() -> (SignalWatcher<T>, SignalStream<T>) {
+        let slot = Arc::new(
+            Mutex::new(WatchSlot {
+                next: None,
+                closed: false,
+                waker: None,
+            })
+        );
+        (SignalWatcher { slot: slot.clone() }, SignalStream { slot })
+    }
+
+    /// Wait for the next change. Returns `None` once the stream has ended.
+    pub async fn changed(&mut self) -> Option<Option<T>> {
+        self.next().await
+    }
+
+    /// Wait until the value passes the predicate, e.g. `wait_for(|closed| *closed)`. Returns `None`
+    /// if the stream ends first.
+    pub async fn wait_for(&mut self, predicate: impl Fn(&T) -> bool) -> Option<T> {
+        while let Some(next) = self.next().await {
+            if let Some(data) = next.filter(|data| predicate(data)) {
+                return Some(data);
+            }
+        }
+        None
+    }
+}
+
+impl<T: LazySignalsData> Stream for SignalStream<T> {
+    type Item = Option<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut slot = self.slot.lock().unwrap();
+        if let Some(next) = slot.next.take() {
+            return Poll::Ready(Some(next));
+        }
+        if slot.closed {
+            return Poll::Ready(None);
+        }
+        slot.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// The sending half of a `SignalStream`, kept by the `LazySignalsState` that is being watched.
+/// Dropping it (e.g. when the entity is despawned) ends the stream.
+pub struct SignalWatcher<T: LazySignalsData> {
+    slot: Arc<Mutex<WatchSlot<T>>>,
+}
+
+impl<T: LazySignalsData> SignalWatcher<T> {
+    /// Is the `SignalStream` still around to receive values?
+    pub fn is_watched(&self) -> bool {
+        Arc::strong_count(&self.slot) > 1
+    }
+
+    /// Replace the unseen value, if any, and wake the task that is waiting for it.
+    pub fn send(&self, data: Option<T>) {
+        let waker = {
+            let mut slot = self.slot.lock().unwrap();
+            slot.next = Some(data);
+            slot.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T: LazySignalsData> Drop for SignalWatcher<T> {
+    fn drop(&mut self) {
+        let waker = {
+            let mut slot = self.slot.lock().unwrap();
+            slot.closed = true;
+            slot.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::tasks::futures_lite::future::{ block_on, poll_once };
+
+    use crate::{ prelude::*, testing::* };
+
+    #[test]
+    fn only_the_latest_value_is_seen() {
+        let mut app = app();
+        let signal = LazySignals.state::<u32>(1, &mut app.world_mut().commands());
+        let mut stream = LazySignals.watch(signal, &mut app.world_mut().commands());
+        update(&mut app);
+
+        for value in [2, 3] {
+            LazySignals.send(signal, value, &mut app.world_mut().commands());
+            update(&mut app);
+        }
+
+        assert_eq!(block_on(stream.changed()), Some(Some(3)));
+        assert_eq!(block_on(poll_once(stream.changed())), None);
+    }
+
+    #[test]
+    fn stream_ends_when_the_signal_is_despawned() {
+        let mut app = app();
+        let signal = LazySignals.state::<u32>(1, &mut app.world_mut().commands());
+        let mut stream = LazySignals.watch(signal, &mut app.world_mut().commands());
+        update(&mut app);
+
+        LazySignals.send(signal, 2, &mut app.world_mut().commands());
+        update(&mut app);
+        app.world_mut().despawn(signal.entity());
+        update(&mut app);
+
+        // whatever was sent before is still delivered
+        assert_eq!(block_on(stream.changed()), Some(Some(2)));
+        assert_eq!(block_on(stream.changed()), None);
+    }
+}
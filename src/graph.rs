@@ -0,0 +1,196 @@
+use bevy::{ ecs::{ storage::SparseSet, world::World }, prelude::* };
+
+use crate::framework::*;
+
+/// Look for a path from the given sources back to the entity that is about to depend on them. The
+/// path starts and ends with that entity, e.g. `[a, b, a]` when `a` uses `b` and `b` uses `a`.
+///
+/// Only `Computed`s can be part of a cycle since nothing else has sources and a value. Faulted
+/// nodes never run, so they do not count.
+pub fn find_cycle(entity: Entity, sources: &[Entity], world: &World) -> Option<Vec<Entity>> {
+    // each node found so far, mapped to the node that uses it (to rebuild the path)
+    let mut parents = SparseSet::<Entity, Entity>::new();
+    let mut stack = Vec::<Entity>::new();
+
+    for source in sources {
+        if !parents.contains(*source) {
+            parents.insert(*source, entity);
+            stack.push(*source);
+        }
+    }
+
+    while let Some(node) = stack.pop() {
+        if node == entity {
+            // walk the parents back to the entity we started from
+            let mut path = vec![entity];
+            let mut current = *parents.get(node).unwrap();
+            while current != entity {
+                path.push(current);
+                current = *parents.get(current).unwrap();
+            }
+            path.push(entity);
+
+            // each node in the path uses the next one
+            path.reverse();
+            return Some(path);
+        }
+
+        let Some(handle) = world.get_entity(node) else {
+            continue;
+        };
+        if handle.contains::<Faulted>() {
+            continue;
+        }
+        if let Some(computed) = handle.get::<ComputedImmutable>() {
+            for source in computed.sources.iter() {
+                if !parents.contains(*source) {
+                    parents.insert(*source, node);
+                    stack.push(*source);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Find the height of a node in the dependency graph, i.e. one more than its highest source, where a
+/// state is 0. A cached `Rank` is trusted unless the node is still waiting on `InitDependencies`.
+pub fn find_height(entity: Entity, world: &World, heights: &mut SparseSet<Entity, u32>) -> u32 {
+    if let Some(height) = heights.get(entity) {
+        return *height;
+    }
+
+    let Some(handle) = world.get_entity(entity) else {
+        return 0;
+    };
+    if !handle.contains::<InitDependencies>() || handle.contains::<Faulted>() {
+        if let Some(rank) = handle.get::<Rank>() {
+            return rank.height;
+        }
+    }
+
+    let mut sources = Vec::<Entity>::new();
+    if let Some(computed) = handle.get::<ComputedImmutable>() {
+        sources.extend(computed.sources.iter());
+    }
+    if let Some(effect) = handle.get::<LazyEffect>() {
+        sources.extend(effect.sources.iter());
+        sources.extend(effect.triggers.iter());
+    }
+
+    // placeholder so a cycle that was not rejected yet can not recurse forever
+    heights.insert(entity, 0);
+    let height = sources
+        .iter()
+        .map(|source| find_height(*source, world, heights) + 1)
+        .max()
+        .unwrap_or(0);
+    heights.insert(entity, height);
+    height
+}
+
+/// Follow a map of each node to the node that notified it back to where it started. The path
+/// starts with the origin (usually a signal) and ends with the given entity.
+pub fn path_to(entity: Entity, parents: &SparseSet<Entity, Entity>) -> Vec<Entity> {
+    let mut path = vec![entity];
+    let mut current = entity;
+    while let Some(parent) = parents.get(current) {
+        // stop if the path loops back on itself
+        if path.contains(parent) {
+            break;
+        }
+        path.push(*parent);
+        current = *parent;
+    }
+
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ prelude::*, testing::* };
+
+    use super::*;
+
+    // a state `a` and a chain of computeds `b` uses `a`, `c` uses `b`
+    fn chain(app: &mut App) -> (Entity, Entity, Entity) {
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let a = LazySignals.state::<u32>(1, &mut commands);
+        let b = LazySignals.computed::<(Option<u32>,), u32>(
+            |(a,)| LazySignals::result(a.unwrap_or(0)),
+            (a,),
+            &mut commands
+        );
+        let c = LazySignals.computed::<(Option<u32>,), u32>(
+            |(b,)| LazySignals::result(b.unwrap_or(0)),
+            (b,),
+            &mut commands
+        );
+        update(app);
+        (a.entity(), b.entity(), c.entity())
+    }
+
+    #[test]
+    fn cycle_path_starts_and_ends_with_the_entity() {
+        let mut app = app();
+        let (a, b, c) = chain(&mut app);
+        let world = app.world();
+        assert_eq!(find_cycle(b, &[c], world), Some(vec![b, c, b]));
+        assert_eq!(find_cycle(c, &[c], world), Some(vec![c, c]));
+        assert_eq!(find_cycle(b, &[a], world), None);
+    }
+
+    #[test]
+    fn faulted_nodes_do_not_close_a_cycle() {
+        let mut app = app();
+        let (_, b, c) = chain(&mut app);
+        app.world_mut()
+            .entity_mut(c)
+            .insert(Faulted { error: LazySignalsError::NoSignalError });
+        assert_eq!(find_cycle(b, &[c], app.world()), None);
+    }
+
+    #[test]
+    fn path_leads_back_to_the_origin() {
+        let [a, b, c] = [1, 2, 3].map(Entity::from_raw);
+        let mut parents = SparseSet::new();
+        parents.insert(c, b);
+        parents.insert(b, a);
+        assert_eq!(path_to(c, &parents), vec![a, b, c]);
+        assert_eq!(path_to(a, &parents), vec![a]);
+
+        // a loop in the parents stops at the first repeat
+        parents.insert(a, c);
+        assert_eq!(path_to(c, &parents), vec![a, b, c]);
+    }
+
+    #[test]
+    fn height_is_one_more_than_the_highest_source() {
+        let mut app = app();
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let a = LazySignals.state::<u32>(1, &mut commands);
+        let b = LazySignals.computed::<(Option<u32>,), u32>(
+            |(a,)| LazySignals::result(a.unwrap_or(0)),
+            (a,),
+            &mut commands
+        );
+        let c = LazySignals.computed::<(Option<u32>, Option<u32>), u32>(
+            |(a, b)| LazySignals::result(a.unwrap_or(0) + b.unwrap_or(0)),
+            (a, b),
+            &mut commands
+        );
+        update(&mut app);
+
+        let world = app.world();
+        let mut heights = SparseSet::new();
+        assert_eq!(find_height(a.entity(), world, &mut heights), 0);
+        assert_eq!(find_height(b.entity(), world, &mut heights), 1);
+        assert_eq!(find_height(c.entity(), world, &mut heights), 2);
+        assert_eq!(world.get::<Rank>(c.entity()).map(|rank| rank.height), Some(2));
+    }
+}
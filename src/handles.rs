@@ -0,0 +1,245 @@
+use std::{ fmt::{ self, Debug }, hash::{ Hash, Hasher }, marker::PhantomData };
+
+use bevy::{ prelude::*, utils::all_tuples };
+
+use crate::framework::*;
+
+/// ## Handles
+/// Typed wrappers around the `Entity` of each `LazySignals` primitive, so the compiler can check
+/// the data type of a read or a send instead of quietly returning `None` at runtime.
+///
+/// Every handle converts into its `Entity` with `into()` (or `entity()`), and a raw `Entity` still
+/// works anywhere a handle does (it just isn't checked).
+///
+/// A `Signal` state (or reducer) that holds a `T`. This is the only handle that can be sent to.
+pub struct Signal<T> {
+    entity: Entity,
+    data_type: PhantomData<fn() -> T>,
+}
+
+/// A `Computed` that memoizes a `T`.
+pub struct Memo<T> {
+    entity: Entity,
+    data_type: PhantomData<fn() -> T>,
+}
+
+/// A `Signal` stream that delivers every `T` sent in a tick as a `Vec<T>`.
+pub struct StreamHandle<T> {
+    entity: Entity,
+    data_type: PhantomData<fn() -> T>,
+}
+
+/// An `Effect`, which has no data of its own.
+pub struct EffectHandle {
+    entity: Entity,
+}
+
+/// An `Action`, which has no data of its own.
+pub struct ActionHandle {
+    entity: Entity,
+}
+
+// derives would require `T` to implement each trait too, even though only the entity is stored
+macro_rules! impl_handle {
+    ($handle:ident $(<$T:ident>)?, $doc:literal) => {
+        impl$(<$T>)? $handle$(<$T>)? {
+            #[doc = $doc]
+            pub fn from_entity(entity: Entity) -> Self {
+                Self { entity, $(data_type: PhantomData::<fn() -> $T>)? }
+            }
+
+            pub fn entity(&self) -> Entity {
+                self.entity
+            }
+        }
+
+        impl$(<$T>)? Clone for $handle$(<$T>)? {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl$(<$T>)? Copy for $handle$(<$T>)? {}
+
+        impl$(<$T>)? Debug for $handle$(<$T>)? {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, stringify!($handle))?;
+                $(write!(f, "<{}>", std::any::type_name::<$T>())?;)?
+                write!(f, "({:?})", self.entity)
+            }
+        }
+
+        impl$(<$T>)? PartialEq for $handle$(<$T>)? {
+            fn eq(&self, other: &Self) -> bool {
+                self.entity == other.entity
+            }
+        }
+
+        impl$(<$T>)? Eq for $handle$(<$T>)? {}
+
+        impl$(<$T>)? Hash for $handle$(<$T>)? {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.entity.hash(state);
+            }
+        }
+
+        impl$(<$T>)? From<$handle$(<$T>)?> for Entity {
+            fn from(handle: $handle$(<$T>)?) -> Self {
+                handle.entity
+            }
+        }
+    };
+}
+
+impl_handle!(
+    Signal<T>,
+    "Wrap an entity that is already known to hold a `LazySignalsState<T>` (this is not checked)."
+);
+impl_handle!(
+    Memo<T>,
+    "Wrap an entity that is already known to be a `Computed` of `T` (this is not checked)."
+);
+impl_handle!(
+    StreamHandle<T>,
+    "Wrap an entity that is already known to hold a `LazySignalsStream<T>` (this is not checked)."
+);
+impl_handle!(
+    EffectHandle,
+    "Wrap an entity that is already known to be an `Effect` (this is not checked)."
+);
+impl_handle!(
+    ActionHandle,
+    "Wrap an entity that is already known to be an `Action` (this is not checked)."
+);
+
+/// ## Traits
+/// Something that can be read as the arg `A` of a `Computed`, `Effect`, or `Action` (e.g. a
+/// `Signal<T>` is an `Option<T>`, or a `Result<Option<T>, LazySignalsError>` in results mode).
+pub trait LazySignalsSource<A>: Copy {
+    fn source_entity(self) -> Entity;
+}
+
+/// Something that a value of type `T` can be sent to.
+pub trait LazySignalsTarget<T>: Copy {
+    fn target_entity(self) -> Entity;
+}
+
+/// The sources of a `Computed`, `Effect`, or `Action` whose args tuple is `P`. A tuple of handles
+/// is checked against `P` at compile time, while a `Vec<Entity>` is only checked at runtime.
+pub trait LazySignalsSources<P> {
+    fn into_entities(self) -> Vec<Entity>;
+}
+
+impl<A> LazySignalsSource<A> for Entity {
+    fn source_entity(self) -> Entity {
+        self
+    }
+}
+
+impl<T: LazySignalsData> LazySignalsSource<Option<T>> for Signal<T> {
+    fn source_entity(self) -> Entity {
+        self.entity
+    }
+}
+
+impl<T: LazySignalsData> LazySignalsSource<Result<Option<T>, LazySignalsError>> for Signal<T> {
+    fn source_entity(self) -> Entity {
+        self.entity
+    }
+}
+
+impl<T: LazySignalsData> LazySignalsSource<Option<T>> for Memo<T> {
+    fn source_entity(self) -> Entity {
+        self.entity
+    }
+}
+
+impl<T: LazySignalsData> LazySignalsSource<Result<Option<T>, LazySignalsError>> for Memo<T> {
+    fn source_entity(self) -> Entity {
+        self.entity
+    }
+}
+
+impl<T: LazySignalsData> LazySignalsSource<Vec<T>> for StreamHandle<T> {
+    fn source_entity(self) -> Entity {
+        self.entity
+    }
+}
+
+impl<T: LazySignalsData> LazySignalsSource<Result<Vec<T>, LazySignalsError>> for StreamHandle<T> {
+    fn source_entity(self) -> Entity {
+        self.entity
+    }
+}
+
+impl<T> LazySignalsTarget<T> for Entity {
+    fn target_entity(self) -> Entity {
+        self
+    }
+}
+
+impl<T: LazySignalsData> LazySignalsTarget<T> for Signal<T> {
+    fn target_entity(self) -> Entity {
+        self.entity
+    }
+}
+
+impl<P> LazySignalsSources<P> for Vec<Entity> {
+    fn into_entities(self) -> Vec<Entity> {
+        self
+    }
+}
+
+impl LazySignalsSources<()> for () {
+    fn into_entities(self) -> Vec<Entity> {
+        Vec::new()
+    }
+}
+
+// a tuple of sources, each of which is checked against the arg at the same position
+macro_rules! impl_lazy_signals_sources {
+    ($(($A:ident, $S:ident)),*) => {
+        impl<$($A, $S: LazySignalsSource<$A>),*> LazySignalsSources<($($A,)*)> for ($($S,)*) {
+            #[allow(non_snake_case)]
+            fn into_entities(self) -> Vec<Entity> {
+                let ($($S,)*) = self;
+                vec![$($S.source_entity()),*]
+            }
+        }
+    };
+}
+
+all_tuples!(impl_lazy_signals_sources, 1, 12, A, S);
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    // the prelude brings the source and target traits into scope along with the handles
+    use crate::prelude::*;
+
+    #[test]
+    fn every_handle_has_an_entity() {
+        let entity = Entity::from_raw(7);
+        assert_eq!(Signal::<u32>::from_entity(entity).entity(), entity);
+        assert_eq!(Memo::<u32>::from_entity(entity).entity(), entity);
+        assert_eq!(StreamHandle::<u32>::from_entity(entity).entity(), entity);
+        assert_eq!(EffectHandle::from_entity(entity).entity(), entity);
+        assert_eq!(ActionHandle::from_entity(entity).entity(), entity);
+    }
+
+    #[test]
+    fn handles_convert_into_their_entity() {
+        let entity = Entity::from_raw(7);
+        let signal = Signal::<u32>::from_entity(entity);
+        assert_eq!(Entity::from(signal), entity);
+        assert_eq!(LazySignalsSource::<Option<u32>>::source_entity(signal), entity);
+        assert_eq!(LazySignalsTarget::<u32>::target_entity(signal), entity);
+        assert_eq!(Entity::from(EffectHandle::from_entity(entity)), entity);
+        assert_eq!(format!("{:?}", signal), format!("Signal<u32>({:?})", entity));
+        assert_eq!(
+            format!("{:?}", EffectHandle::from_entity(entity)),
+            format!("EffectHandle({:?})", entity)
+        );
+    }
+}
@@ -2,24 +2,45 @@ use bevy::{ ecs::schedule::SystemConfigs, prelude::* };
 
 mod arcane_wizardry;
 
+mod graph;
+
+#[cfg(test)]
+mod testing;
+
 pub mod api;
 
 pub mod commands;
 
+pub mod conditions;
+
 pub mod framework;
 use framework::*;
 use lazy_immutable::*;
 
+pub mod handles;
+
+pub mod params;
+
 pub mod systems;
 use systems::{
     computed::compute_memos,
+    dispose::dispose_lazy_signals,
     init::init_lazy_signals,
     signal::send_signals,
     effect::{ apply_deferred_effects, check_tasks },
+    stream::drain_streams,
 };
 
 pub mod prelude {
-    pub use crate::{ api::*, framework::*, systems::*, LazySignalsPlugin };
+    pub use crate::{
+        api::*,
+        conditions::*,
+        framework::*,
+        handles::*,
+        params::*,
+        systems::*,
+        LazySignalsPlugin,
+    };
 }
 
 /// Convenience typedefs.
@@ -30,6 +51,7 @@ pub type LazySignalsInt = LazySignalsState<u32>;
 pub type LazySignalsFloat = LazySignalsState<f64>;
 pub type LazySignalsStr = LazySignalsState<StaticStrRef>;
 pub type LazySignalsUnit = LazySignalsState<()>; // for triggers, mostly
+pub type LazySignalsShared<T> = LazySignalsState<Shared<T>>; // for big payloads
 
 /// A reference implementation follows. A developer can replace any or all pieces and provide a new
 /// plugin if so desired.
@@ -40,12 +62,27 @@ pub struct LazySignalsSystemSet;
 
 /// Convenience functions to make it easy to run the `LazySignals` systems when needed.
 pub fn lazy_signals_full_systems() -> SystemConfigs {
-    (check_tasks, init_lazy_signals, send_signals, compute_memos, apply_deferred_effects).chain()
+    (
+        check_tasks,
+        dispose_lazy_signals,
+        drain_streams,
+        init_lazy_signals,
+        send_signals,
+        compute_memos,
+        apply_deferred_effects,
+    ).chain()
 }
 
 /// This chain omits the effects sending system to allow the developer to trigger it a lot if needed.
 pub fn lazy_signals_flush_systems() -> SystemConfigs {
-    (check_tasks, init_lazy_signals, send_signals, compute_memos).chain()
+    (
+        check_tasks,
+        dispose_lazy_signals,
+        drain_streams,
+        init_lazy_signals,
+        send_signals,
+        compute_memos,
+    ).chain()
 }
 
 /// `Plugin` to initialize the resource and system schedule.
@@ -53,7 +90,8 @@ pub struct LazySignalsPlugin;
 
 impl Plugin for LazySignalsPlugin {
     fn build(&self, app: &mut App) {
-        // NOTE: the user application will need to register each custom `LazyImmutable<T>` for reflection
+        // custom `LazySignalsState<T>` and `LazySignalsStream<T>` types do not need to be
+        // registered for reflection: the create commands add them to the `LazySignalsRegistry`
 
         // add the systems to process signals, memos, and effects
         app.add_systems(
@@ -67,9 +105,19 @@ impl Plugin for LazySignalsPlugin {
             // in that case, use `lazy_signals_flush_systems()` to schedule the needed updates
 
             // Last, call `apply_deferred_effects()` at the end so they only fire once per tick
+            // (streams are only drained by the first pass of the next tick, so every effect sees
+            // the whole tick's values and so can any system that runs later in the frame)
             lazy_signals_full_systems().in_set(LazySignalsSystemSet)
         )
-            // custom Immutable types must be manually registered
+            // limits to catch feedback loops (insert a different `LazySignalsConfig` to change them)
+            .init_resource::<LazySignalsConfig>()
+            .init_resource::<LazySignalsBudget>()
+            // where the tasks of actions report when they complete
+            .init_resource::<LazySignalsCompletions>()
+            .add_event::<LazySignalsLoopDetected>()
+            // vtables of every concrete observable type, filled in by the create commands
+            .init_resource::<LazySignalsRegistry>()
+            // only needed to see the convenience types in scenes or inspectors
             .register_type::<LazySignalsBool>()
             .register_type::<LazySignalsInt>()
             .register_type::<LazySignalsFloat>()
@@ -0,0 +1,120 @@
+use bevy::{ ecs::system::SystemParam, prelude::* };
+
+use crate::{
+    framework::*,
+    handles::LazySignalsSource,
+    lazy_immutable::{ LazySignalsImmutable, LazySignalsState },
+};
+
+/// ## System Params
+/// Read the values and errors of every `Signal` or `Computed` of type `T` from an ordinary system.
+///
+/// Unlike `LazySignals.read`, this does not need the `World`, so the system only borrows the
+/// `LazySignalsState<T>` components immutably and can run in parallel with other readers.
+///
+/// Values only change while the `LazySignals` systems run (`PreUpdate` by default), so anything
+/// sent during `Update` is not visible here until the next batch.
+#[derive(SystemParam)]
+pub struct SignalReader<'w, 's, T: LazySignalsData> {
+    states: Query<'w, 's, &'static LazySignalsState<T>>,
+    faults: Query<'w, 's, &'static Faulted>,
+}
+
+impl<'w, 's, T: LazySignalsData> SignalReader<'w, 's, T> {
+    /// Alias for value.
+    pub fn get(&self, immutable: impl LazySignalsSource<Option<T>>) -> Option<T> {
+        self.value(immutable)
+    }
+
+    /// Check the given entity for an error.
+    pub fn get_error(
+        &self,
+        immutable: impl LazySignalsSource<Option<T>>
+    ) -> Option<LazySignalsError> {
+        let entity = immutable.source_entity();
+
+        // a `Computed` that can not run reports why
+        if let Ok(faulted) = self.faults.get(entity) {
+            return Some(faulted.error.clone());
+        }
+
+        match self.states.get(entity) {
+            Ok(observable) => observable.error(),
+            Err(_) => None,
+        }
+    }
+
+    /// Alias for value.
+    pub fn read(&self, immutable: impl LazySignalsSource<Option<T>>) -> Option<T> {
+        self.value(immutable)
+    }
+
+    /// Get the current value or error, like the args of a `Computed` with results.
+    pub fn result(
+        &self,
+        immutable: impl LazySignalsSource<Option<T>>
+    ) -> Result<Option<T>, LazySignalsError> {
+        let entity = immutable.source_entity();
+        if let Ok(faulted) = self.faults.get(entity) {
+            return Err(faulted.error.clone());
+        }
+
+        match self.states.get(entity) {
+            Ok(observable) =>
+                match observable.error() {
+                    Some(error) => Err(error),
+                    None => Ok(observable.get()),
+                }
+            Err(_) => Err(LazySignalsError::NoSignalError),
+        }
+    }
+
+    /// Get the current value.
+    pub fn value(&self, immutable: impl LazySignalsSource<Option<T>>) -> Option<T> {
+        match self.states.get(immutable.source_entity()) {
+            Ok(observable) => observable.get(),
+            Err(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ prelude::*, testing::* };
+
+    use super::*;
+
+    #[derive(Resource, Default)]
+    struct Read(Vec<Result<Option<u32>, LazySignalsError>>);
+
+    #[test]
+    fn reader_sees_values_and_errors() {
+        let mut app = app();
+        app.init_resource::<Read>();
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let state = LazySignals.state::<u32>(1, &mut commands);
+        let failed = LazySignals.state::<u32>(2, &mut commands);
+        LazySignals.send_error::<u32>(failed, LazySignalsError::user("nope"), &mut commands);
+        let missing = commands.spawn_empty().id();
+        app.add_systems(Update, move |reader: SignalReader<u32>, mut read: ResMut<Read>| {
+            read.0 = vec![reader.result(state), reader.result(failed), reader.result(missing)];
+        });
+        update(&mut app);
+
+        assert_eq!(
+            app.world().resource::<Read>().0,
+            vec![
+                Ok(Some(1)),
+                Err(LazySignalsError::user("nope")),
+                Err(LazySignalsError::NoSignalError)
+            ]
+        );
+
+        // a value sent during the frame is seen once the batch ran
+        LazySignals.send(state, 3, &mut app.world_mut().commands());
+        update(&mut app);
+        assert_eq!(app.world().resource::<Read>().0[0], Ok(Some(3)));
+    }
+}
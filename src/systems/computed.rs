@@ -1,157 +1,562 @@
-use bevy::{ ecs::world::World, prelude::*, reflect::DynamicTuple };
+use std::{ cmp::Reverse, collections::BinaryHeap, sync::Mutex };
+
+use bevy::{
+    ecs::{ storage::SparseSet, world::World },
+    prelude::*,
+    reflect::DynamicTuple,
+    tasks::{ ComputeTaskPool, TaskPool },
+};
 
 use crate::{ arcane_wizardry::*, framework::* };
 
+type MemoParam<'a> = (
+    Entity,
+    &'a ImmutableState,
+    &'a ComputedImmutable,
+    Option<&'a Rank>,
+    Has<TrackedSources>,
+    Has<ForceCompute>,
+);
+
+// a computed that is ready to run, along with its args and the first upstream error, if any
+type MemoJob = (Entity, DynamicTuple, Option<LazySignalsError>);
+
+// what to store for a computed once the world can be written to again
+type MemoStore = Option<(Entity, Box<dyn ComputedStore>)>;
+
+// run the computed closure without touching the world and return what to store afterwards
+fn run_memo((computed, args, upstream_error): MemoJob, world: &World) -> MemoStore {
+    trace!("***COMPUTE*** {:?}", computed);
+    let computed_immutable = world.get::<ComputedImmutable>(computed)?;
+    let store = computed_immutable.function.lock().unwrap()(args, upstream_error, world);
+    Some((computed, store))
+}
+
 // recompute all the dirty computeds
 pub fn compute_memos(
     world: &mut World,
-    query_memos: &mut QueryState<(Entity, &ImmutableState, &ComputedImmutable), With<ComputeMemo>>
+    query_memos: &mut QueryState<MemoParam, (With<ComputeMemo>, Without<Faulted>)>,
+    mut component_id_set: Local<ComponentIdSet>
 ) {
     trace!("MEMOS");
 
-    let mut component_id_set = ComponentIdSet::new();
-    let mut component_info_set = ComponentInfoSet::new();
+    component_id_set.clear();
     let mut processed = empty_set();
     let mut sources = EntityRelationshipSet::new();
-    let mut stack = Vec::<Entity>::new();
+    let mut args_modes = SparseSet::<Entity, ArgsMode>::new();
+    let mut heights = SparseSet::<Entity, u32>::new();
+    let mut queue = BinaryHeap::<Reverse<(u32, Entity)>>::new();
+    let mut tracked = empty_set();
+    let mut forced = empty_set();
+    let mut rerun_changed = empty_set();
 
-    query_memos.iter(world).for_each(|(entity, immutable, computed)| {
+    query_memos.iter(world).for_each(|(entity, immutable, computed, rank, is_tracked, is_forced)| {
         let component_id = immutable.component_id;
         trace!("-found computed {:#?} with component ID {:?}", entity, component_id);
         component_id_set.insert(entity, component_id);
-        if let Some(info) = world.components().get_info(component_id) {
-            component_info_set.insert(component_id, info.clone());
-        }
 
         sources.insert(entity, computed.sources.clone());
+        args_modes.insert(entity, computed.args_mode);
+        if is_tracked {
+            tracked.insert(entity, ());
+        }
+        if is_forced {
+            forced.insert(entity, ());
+        }
 
-        // doesn't matter what order we evaluate things in since it all has to get resolved
-
-        // the value of each computed memo is deterministic since the data is immutable
-        stack.push(entity);
+        // lowest rank first, so every source is up to date before anything that uses it runs
+        let height = rank.map_or(0, |rank| rank.height);
+        heights.insert(entity, height);
+        queue.push(Reverse((height, entity)));
     });
 
-    // main loop: evaluate highest index (pop the stack)
-    while let Some(computed) = stack.pop() {
-        trace!("COMPUTED {:?}", computed);
-        // do not run this Computed if already in the processed set
-        if processed.contains(computed) {
-            trace!("-skipping");
-            continue;
+    // main loop: evaluate the lowest rank (pop every computed of that rank off the queue)
+    while let Some(Reverse((height, _))) = queue.peek().copied() {
+        trace!("RANK {}", height);
+        let mut level = Vec::<Entity>::new();
+        while queue.peek().is_some_and(|Reverse((next, _))| *next == height) {
+            let Reverse((_, computed)) = queue.pop().unwrap();
+
+            // do not run this Computed if already in the processed set (or already in this rank)
+            if processed.contains(computed) || level.contains(&computed) {
+                trace!("-skipping {:?}", computed);
+                continue;
+            }
+            level.push(computed);
         }
 
-        let sources = sources.get(computed).unwrap();
-        let mut dirty_sources = Vec::<Entity>::new();
-        for source in sources {
-            trace!("-checking source for dirt: {:?}", source);
-            let source = *source;
-            if world.entity(source).contains::<Dirty>() {
-                trace!("- - - durrrrty - - -");
-                dirty_sources.push(source);
+        // read: figure out which computeds of this rank can run
+        let mut ready = Vec::<Entity>::new();
+        let mut unchanged = Vec::<Entity>::new();
+        for computed in level {
+            trace!("COMPUTED {:?}", computed);
+            let sources = sources.get(computed).unwrap();
+
+            // a source may have been despawned since the last time dispose_lazy_signals ran
+            if !sources.iter().all(|source| is_observable(source, world)) {
+                trace!("-missing source");
+                world.resource_scope(|world, registry: Mut<LazySignalsRegistry>| {
+                    fault_derived(&computed, LazySignalsError::NoSignalError, &registry, world);
+                });
+                processed.insert(computed, ());
+                continue;
+            }
+
+            // a source that still needs computing means the cached rank is stale (e.g. a source was
+            // re-wired onto something higher), so fix the rank and come back after that source
+            let pending_height = sources
+                .iter()
+                .filter(|source| heights.contains(**source) && !processed.contains(**source))
+                .map(|source| *heights.get(*source).unwrap())
+                .max();
+
+            if let Some(height) = pending_height {
+                let height = height + 1;
+                trace!("-re-ranking to {}", height);
+                heights.insert(computed, height);
+                world.entity_mut(computed).insert(Rank { height });
+                queue.push(Reverse((height, computed)));
+            } else if
+                forced.contains(computed) ||
+                sources.iter().any(|source| world.entity(*source).contains::<ValueChanged>())
+            {
+                // otherwise, if all sources are up to date, then it can be recomputed
+                ready.push(computed);
+            } else {
+                // nothing it reads changed this batch, so the value it has is still good
+                trace!("-no changed sources");
+                unchanged.push(computed);
             }
         }
 
-        // if any sources are marked dirty, push them on the stack, after the memo
-        if !dirty_sources.is_empty() {
-            trace!("-pushing on the stack");
-            stack.push(computed);
-            stack.append(&mut dirty_sources);
-        } else {
-            // otherwise, if all sources are up to date, then recompute
-            trace!("***COMPUTE***");
-            // build component id -> info map (might already have some but be on the safe side)
-            for source in sources.iter() {
-                let immutable = world.entity(*source).get::<ImmutableState>().unwrap();
-                let component_id = immutable.component_id;
-                trace!("-found a computed source with component ID {:#?}", component_id);
-                component_id_set.insert(*source, component_id);
-                if let Some(info) = world.components().get_info(component_id) {
-                    component_info_set.insert(component_id, info.clone());
+        // read some more: prepare the args of each computed that is ready
+        let mut jobs = Vec::<MemoJob>::new();
+        world.resource_scope(|world, registry: Mut<LazySignalsRegistry>| {
+            for computed in unchanged {
+                // make sure computeds refresh so they will be notified next time
+                for source in sources.get(computed).unwrap().iter() {
+                    subscribe(&computed, source, &registry, world);
                 }
+                processed.insert(computed, ());
+
+                // a tracked computed that already ran once this batch may still have changed
+                let mut handle = world.entity_mut(computed);
+                if rerun_changed.contains(computed) {
+                    handle.insert(ValueChanged);
+                }
+                handle.remove::<(ComputeMemo, Dirty)>();
             }
 
-            // remove the ComputeMemo component
-            world.entity_mut(computed).remove::<ComputeMemo>();
+            for computed in ready {
+                let sources = sources.get(computed).unwrap();
+
+                // build entity -> component id map (might already have some but be on the safe side)
+                for source in sources.iter() {
+                    let immutable = world.entity(*source).get::<ImmutableState>().unwrap();
+                    let component_id = immutable.component_id;
+                    trace!("-found a computed source with component ID {:#?}", component_id);
+                    component_id_set.insert(*source, component_id);
+                }
 
-            world.resource_scope(|world, type_registry: Mut<AppTypeRegistry>| {
-                let type_registry = type_registry.read();
+                // remove the ComputeMemo component
+                world.entity_mut(computed).remove::<(ComputeMemo, ForceCompute)>();
 
                 // prepare the args
+                let args_mode = *args_modes.get(computed).unwrap();
                 let mut args = DynamicTuple::default();
+                let mut upstream_error = Option::<LazySignalsError>::None;
                 for source in sources.iter() {
                     trace!("Processing source {:?}", source);
                     let component_id = component_id_set.get(*source).unwrap();
-                    let type_id = component_info_set.get(*component_id).unwrap().type_id().unwrap();
 
-                    // call the copy_data method via reflection
+                    // call the copy_data method through the registered vtable
                     // this will append the source data to the args tuple
-                    // FIXME indicate an error if the args don't line up?
-                    if let Some(mut source) = world.get_entity_mut(*source) {
+                    // (the args were checked against the sources when this was created)
+                    // a tracked computed reads its sources itself, so it only needs to subscribe
+                    if tracked.contains(computed) {
+                        trace!("-tracked");
+                    } else if let Some(mut source) = world.get_entity_mut(*source) {
                         // insert arcane wizardry here
                         run_as_observable(
                             &mut source,
                             Some(&mut args),
                             Some(&computed),
                             component_id,
-                            &type_id,
-                            &type_registry,
+                            &registry,
                             Box::new(|observable, args, target| {
-                                observable.copy_data(*target.unwrap(), args.unwrap());
+                                match args_mode {
+                                    ArgsMode::Options => {
+                                        observable.copy_data(*target.unwrap(), args.unwrap());
+                                    }
+                                    ArgsMode::Results => {
+                                        observable.copy_result(*target.unwrap(), args.unwrap());
+                                    }
+                                }
+
+                                // keep the first error so the computed can pass it through
+                                if upstream_error.is_none() {
+                                    upstream_error = observable.copy_error();
+                                }
                                 None
                             })
                         );
                     }
 
                     // make sure computeds refresh so they will be notified next time
-                    subscribe(&computed, source, &type_registry, world);
+                    subscribe(&computed, source, &registry, world);
                 }
 
-                let mut changed = false;
-                let mut clean = false;
-
-                // actually compute the computed
-                {
-                    let world = world.as_unsafe_world_cell();
-                    if let Some(handle) = world.get_entity(computed) {
-                        // safety (from the docs):
-                        // -the UnsafeEntityCell has permission to access the component mutably
-                        // -no other references to the component exist at the same time
-                        unsafe {
-                            let computed_immutable = handle.get_mut::<ComputedImmutable>().unwrap();
-
-                            // I think this world must not be used to mutate the computed, not sure
-                            if
-                                computed_immutable.function
-                                    .lock()
-                                    .unwrap()(&args, &computed, world.world_mut())
-                            {
-                                // mark changed if the value actually changed
-                                changed = true;
-                            }
-                        }
-
-                        // add the computed entity to the processed set
-                        processed.insert(computed, ());
+                jobs.push((computed, args, upstream_error));
+            }
+        });
 
-                        // mark the computed not dirty
-                        clean = true;
-                    }
+        // compute: nothing in a rank depends on anything else in the same rank, so run in parallel
+        // (each task fills in its own slot, since the scope can not hand back a boxed store)
+        let stores = if jobs.len() > 1 {
+            let slots = jobs
+                .iter()
+                .map(|_| Mutex::<MemoStore>::new(None))
+                .collect::<Vec<_>>();
+            let world: &World = world;
+            ComputeTaskPool::get_or_init(TaskPool::default).scope(|scope| {
+                for (job, slot) in jobs.into_iter().zip(slots.iter()) {
+                    scope.spawn(async move {
+                        *slot.lock().unwrap() = run_memo(job, world);
+                    });
                 }
+            });
+            slots
+                .into_iter()
+                .map(|slot| slot.into_inner().unwrap())
+                .collect::<Vec<_>>()
+        } else {
+            jobs.into_iter()
+                .map(|job| run_memo(job, world))
+                .collect::<Vec<_>>()
+        };
 
-                if changed || clean {
-                    let mut handle = world.entity_mut(computed);
+        // write: store each result and mark what changed
+        for (computed, store) in stores.into_iter().flatten() {
+            let changed = store(&computed, world);
 
-                    if changed {
-                        trace!("-marking changed");
-                        handle.insert(ValueChanged);
-                    }
+            // a tracked computed may have read something that is still waiting to be computed, in
+            // which case it runs again (with its new rank) after that
+            if tracked.contains(computed) {
+                let new_sources = world
+                    .get::<ComputedImmutable>(computed)
+                    .map(|computed| computed.sources.clone())
+                    .unwrap_or_default();
+                let pending_height = new_sources
+                    .iter()
+                    .filter(|source| heights.contains(**source) && !processed.contains(**source))
+                    .map(|source| *heights.get(*source).unwrap())
+                    .max();
+                sources.insert(computed, new_sources);
+
+                if let Some(height) = pending_height {
+                    if !world.entity(computed).contains::<Faulted>() {
+                        let height = height + 1;
+                        trace!("-tracked a pending source, re-ranking to {}", height);
+                        heights.insert(computed, height);
+                        world.entity_mut(computed).insert(Rank { height });
+                        queue.push(Reverse((height, computed)));
 
-                    if clean {
-                        trace!("-marking not dirty");
-                        handle.remove::<Dirty>();
+                        // whatever it stores next time, it already changed once this batch
+                        if changed {
+                            rerun_changed.insert(computed, ());
+                        }
+                        continue;
                     }
                 }
-            });
+            }
+
+            // add the computed entity to the processed set
+            processed.insert(computed, ());
+
+            let mut handle = world.entity_mut(computed);
+
+            // mark changed if the value actually changed
+            if changed || rerun_changed.contains(computed) {
+                trace!("-marking changed");
+                handle.insert(ValueChanged);
+            }
+
+            // mark the computed not dirty
+            trace!("-marking not dirty");
+            handle.remove::<Dirty>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{ Arc, Mutex };
+
+    use bevy::prelude::*;
+
+    use crate::{ prelude::*, testing::* };
+
+    // the args of each run of a computed
+    type Runs = Arc<Mutex<Vec<(Option<u32>, Option<u32>)>>>;
+
+    #[test]
+    fn diamond_joins_once_with_consistent_inputs() {
+        let mut app = app();
+        let runs = Runs::default();
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let a = LazySignals.state::<u32>(1, &mut commands);
+        let b = LazySignals.computed::<(Option<u32>,), u32>(
+            |(a,)| LazySignals::result(a.unwrap() + 1),
+            (a,),
+            &mut commands
+        );
+        let c = LazySignals.computed::<(Option<u32>,), u32>(
+            |(a,)| LazySignals::result(a.unwrap() * 2),
+            (a,),
+            &mut commands
+        );
+        let join_runs = runs.clone();
+        let d = LazySignals.computed::<(Option<u32>, Option<u32>), u32>(
+            move |(b, c)| {
+                join_runs.lock().unwrap().push((b, c));
+                LazySignals::result(b.unwrap() + c.unwrap())
+            },
+            (b, c),
+            &mut commands
+        );
+        update(&mut app);
+
+        for value in [2, 3] {
+            LazySignals.send(a, value, &mut app.world_mut().commands());
+            update(&mut app);
+        }
+
+        assert_eq!(*runs.lock().unwrap(), vec![(Some(3), Some(4)), (Some(4), Some(6))]);
+        assert_eq!(LazySignals.read::<u32>(d, app.world()), Some(10));
+    }
+
+    #[test]
+    fn same_rank_runs_together_before_the_next_rank() {
+        let mut app = app();
+        // the factor of each computed of the first rank as it runs, then `None` for the sum
+        let order = Arc::new(Mutex::new(Vec::<Option<u32>>::new()));
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let a = LazySignals.state::<u32>(1, &mut commands);
+        let level = (1..=4)
+            .map(|factor| {
+                let level_order = order.clone();
+                LazySignals.computed::<(Option<u32>,), u32>(
+                    move |(a,)| {
+                        level_order.lock().unwrap().push(Some(factor));
+                        LazySignals::result(a.unwrap() * factor)
+                    },
+                    (a,),
+                    &mut commands
+                ).entity()
+            })
+            .collect::<Vec<_>>();
+        let sum_order = order.clone();
+        let sum = LazySignals.computed::<
+            (Option<u32>, Option<u32>, Option<u32>, Option<u32>),
+            u32
+        >(
+            move |(w, x, y, z)| {
+                sum_order.lock().unwrap().push(None);
+                LazySignals::result(w.unwrap() + x.unwrap() + y.unwrap() + z.unwrap())
+            },
+            level,
+            &mut commands
+        );
+        LazySignals.send(a, 2, &mut commands);
+        update(&mut app);
+
+        // every computed of the first rank ran once (in any order), then the one above them
+        let mut order = order.lock().unwrap().clone();
+        assert_eq!(order.pop(), Some(None));
+        order.sort();
+        assert_eq!(order, vec![Some(1), Some(2), Some(3), Some(4)]);
+        assert_eq!(LazySignals.read::<u32>(sum, app.world()), Some(20));
+    }
+
+    #[test]
+    fn unchanged_memo_does_not_recompute_subscribers() {
+        let mut app = app();
+        let runs = Runs::default();
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let a = LazySignals.state::<u32>(0, &mut commands);
+        let parity = LazySignals.computed::<(Option<u32>,), u32>(
+            |(a,)| LazySignals::result(a.unwrap() % 2),
+            (a,),
+            &mut commands
+        );
+        let downstream_runs = runs.clone();
+        let downstream = LazySignals.computed::<(Option<u32>,), u32>(
+            move |(parity,)| {
+                downstream_runs.lock().unwrap().push((parity, None));
+                LazySignals::result(parity.unwrap() * 10)
+            },
+            (parity,),
+            &mut commands
+        );
+        update(&mut app);
+
+        for value in [1, 3, 5, 6] {
+            LazySignals.send(a, value, &mut app.world_mut().commands());
+            update(&mut app);
         }
+
+        assert_eq!(*runs.lock().unwrap(), vec![(Some(1), None), (Some(0), None)]);
+        assert_eq!(LazySignals.read::<u32>(downstream, app.world()), Some(0));
+    }
+
+    #[test]
+    fn rewired_memo_recomputes() {
+        let mut app = app();
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let a = LazySignals.state::<u32>(1, &mut commands);
+        let b = LazySignals.state::<u32>(10, &mut commands);
+        let memo = LazySignals.computed::<(Option<u32>,), u32>(
+            |(source,)| LazySignals::result(source.unwrap() + 1),
+            (a,),
+            &mut commands
+        );
+        LazySignals.send(a, 2, &mut commands);
+        update(&mut app);
+        assert_eq!(LazySignals.read::<u32>(memo, app.world()), Some(3));
+
+        // nothing is sent, but the memo has a new source to read
+        LazySignals.set_sources::<(Option<u32>,)>(memo, (b,), &mut app.world_mut().commands());
+        update(&mut app);
+        assert_eq!(LazySignals.read::<u32>(memo, app.world()), Some(11));
+    }
+
+    fn sources_of(memo: Memo<u32>, app: &App) -> Vec<Entity> {
+        app.world().get::<ComputedImmutable>(memo.entity()).unwrap().sources.clone()
+    }
+
+    #[test]
+    fn tracked_memo_follows_its_branch() {
+        let mut app = app();
+        let runs = Arc::new(Mutex::new(0));
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let flag = LazySignals.state::<bool>(true, &mut commands);
+        let a = LazySignals.state::<u32>(1, &mut commands);
+        let b = LazySignals.state::<u32>(10, &mut commands);
+        let tracked_runs = runs.clone();
+        let memo = LazySignals.computed_tracked::<u32>(
+            move |context| {
+                *tracked_runs.lock().unwrap() += 1;
+                match context.get(flag).unwrap() {
+                    true => LazySignals::option(context.get(a)),
+                    false => LazySignals::option(context.get(b)),
+                }
+            },
+            &mut commands
+        );
+        update(&mut app);
+        assert_eq!(sources_of(memo, &app), vec![flag.entity(), a.entity()]);
+
+        // not read, so not a source
+        LazySignals.send(b, 20, &mut app.world_mut().commands());
+        update(&mut app);
+        assert_eq!(*runs.lock().unwrap(), 1);
+
+        LazySignals.send(flag, false, &mut app.world_mut().commands());
+        update(&mut app);
+        assert_eq!(LazySignals.read::<u32>(memo, app.world()), Some(20));
+        assert_eq!(sources_of(memo, &app), vec![flag.entity(), b.entity()]);
+
+        // the branch that is no longer taken was unsubscribed
+        LazySignals.send(a, 2, &mut app.world_mut().commands());
+        update(&mut app);
+        assert_eq!(*runs.lock().unwrap(), 2);
+
+        LazySignals.send(b, 30, &mut app.world_mut().commands());
+        update(&mut app);
+        assert_eq!(LazySignals.read::<u32>(memo, app.world()), Some(30));
+        assert_eq!(*runs.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn tracked_memo_is_re_ranked_when_it_reads_deeper() {
+        let mut app = app();
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let flag = LazySignals.state::<bool>(true, &mut commands);
+        let a = LazySignals.state::<u32>(1, &mut commands);
+        let doubled = LazySignals.computed::<(Option<u32>,), u32>(
+            |(a,)| LazySignals::result(a.unwrap() * 2),
+            (a,),
+            &mut commands
+        );
+        let memo = LazySignals.computed_tracked::<u32>(
+            move |context| {
+                match context.get(flag).unwrap() {
+                    true => LazySignals::option(context.get(a)),
+                    false => LazySignals::option(context.get(doubled)),
+                }
+            },
+            &mut commands
+        );
+        update(&mut app);
+        assert_eq!(app.world().get::<Rank>(memo.entity()), Some(&Rank { height: 1 }));
+
+        LazySignals.send(flag, false, &mut app.world_mut().commands());
+        update(&mut app);
+        assert_eq!(app.world().get::<Rank>(memo.entity()), Some(&Rank { height: 2 }));
+
+        // it now runs after the computed it reads, so it sees the new value in the same batch
+        LazySignals.send(a, 5, &mut app.world_mut().commands());
+        update(&mut app);
+        assert_eq!(LazySignals.read::<u32>(memo, app.world()), Some(10));
+    }
+
+    #[test]
+    fn results_pass_errors_to_the_closure() {
+        type Args = (Result<Option<u32>, LazySignalsError>, Result<Option<u32>, LazySignalsError>);
+        let mut app = app();
+        let runs = Arc::new(Mutex::new(Vec::<Args>::new()));
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let a = LazySignals.state::<u32>(1, &mut commands);
+        let b = LazySignals.state::<u32>(2, &mut commands);
+        LazySignals.send_error::<u32>(b, LazySignalsError::user("offline"), &mut commands);
+        let results_runs = runs.clone();
+        let memo = LazySignals.computed_with_results::<Args, u32>(
+            move |(a, b)| {
+                results_runs.lock().unwrap().push((a.clone(), b.clone()));
+
+                // an upstream error is just another arg, so it can fall back to a default
+                LazySignals::result(a.unwrap().unwrap_or(0) + b.ok().flatten().unwrap_or(0))
+            },
+            (a, b),
+            &mut commands
+        );
+        update(&mut app);
+        assert_eq!(LazySignals.read::<u32>(memo, app.world()), Some(1));
+
+        LazySignals.send(b, 5, &mut app.world_mut().commands());
+        update(&mut app);
+        assert_eq!(LazySignals.read::<u32>(memo, app.world()), Some(6));
+
+        assert_eq!(
+            *runs.lock().unwrap(),
+            vec![
+                (Ok(Some(1)), Err(LazySignalsError::user("offline"))),
+                (Ok(Some(1)), Ok(Some(5)))
+            ]
+        );
     }
 }
@@ -0,0 +1,230 @@
+use bevy::{ ecs::{ component::ComponentId, system::SystemState, world::World }, prelude::* };
+
+use crate::{ arcane_wizardry::*, framework::* };
+
+type DisposedParam<'w, 's> = (
+    RemovedComponents<'w, 's, ImmutableState>,
+    RemovedComponents<'w, 's, ComputedImmutable>,
+    RemovedComponents<'w, 's, LazyEffect>,
+);
+
+type DependentParam<'a> = (
+    Entity,
+    Option<&'a ComputedImmutable>,
+    Option<&'a LazyEffect>,
+    Has<OwnedBySources>,
+    Has<Faulted>,
+);
+
+// clean up after any signals, computeds, effects, or actions that were despawned
+pub fn dispose_lazy_signals(
+    world: &mut World,
+    removed: &mut SystemState<DisposedParam>,
+    query_dependents: &mut QueryState<DependentParam>,
+    query_observables: &mut QueryState<(Entity, &ImmutableState)>,
+    query_systems: &mut QueryState<(Entity, &OwnedByEffect)>
+) {
+    // build the set of entities that are actually gone (as opposed to just missing a component)
+    let mut disposed = empty_set();
+    {
+        let (mut states, mut computeds, mut effects) = removed.get_mut(world);
+        for entity in states.read().chain(computeds.read()).chain(effects.read()) {
+            disposed.insert(entity, ());
+        }
+    }
+    let mut dead = empty_set();
+    for entity in disposed.indices() {
+        if world.get_entity(entity).is_none() {
+            dead.insert(entity, ());
+        }
+    }
+
+    // nothing to do most of the time
+    if dead.is_empty() {
+        return;
+    }
+
+    trace!("DISPOSE {:?}", dead);
+
+    world.resource_scope(|world, registry: Mut<LazySignalsRegistry>| {
+        // find the dependents of anything that was despawned, until no more get despawned
+        let mut newly_dead: Vec<Entity> = dead.indices().collect();
+        while !newly_dead.is_empty() {
+            let mut orphans = Vec::<(Entity, bool, bool)>::new();
+            query_dependents.iter(world).for_each(|(entity, computed, effect, owned, faulted)| {
+                let mut deps = Vec::<Entity>::new();
+                if let Some(computed) = computed {
+                    deps.extend(computed.sources.iter());
+                }
+                if let Some(effect) = effect {
+                    deps.extend(effect.sources.iter());
+                    deps.extend(effect.triggers.iter());
+                }
+                if deps.iter().any(|dep| newly_dead.contains(dep)) {
+                    orphans.push((entity, owned, faulted));
+                }
+            });
+
+            newly_dead.clear();
+            for (orphan, owned, faulted) in orphans {
+                if owned {
+                    // cascade: the dependent goes away with its source
+                    trace!("-despawning owned dependent {:?}", orphan);
+                    world.despawn(orphan);
+                    dead.insert(orphan, ());
+                    newly_dead.push(orphan);
+                } else if !faulted {
+                    // otherwise it sticks around but can not run anymore
+                    fault_derived(&orphan, LazySignalsError::NoSignalError, &registry, world);
+                }
+            }
+        }
+
+        // remove the one-shot systems registered for any effect that is gone
+        let mut systems = Vec::<Entity>::new();
+        query_systems.iter(world).for_each(|(entity, owner)| {
+            if dead.contains(owner.effect) {
+                systems.push(entity);
+            }
+        });
+        for system in systems {
+            trace!("-despawning effect system {:?}", system);
+            world.despawn(system);
+        }
+
+        // prune the dead from the subscribers of everything that is still alive
+        let mut observables = Vec::<(Entity, ComponentId)>::new();
+        query_observables.iter(world).for_each(|(entity, immutable)| {
+            observables.push((entity, immutable.component_id));
+        });
+
+        for (entity, component_id) in observables {
+            if let Some(mut observable) = world.get_entity_mut(entity) {
+                run_as_observable(
+                    &mut observable,
+                    None,
+                    None,
+                    &component_id,
+                    &registry,
+                    Box::new(|observable, _args, _target| {
+                        for subscriber in dead.indices() {
+                            observable.unsubscribe(subscriber);
+                        }
+                        None
+                    })
+                );
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use crate::{
+        lazy_immutable::{ LazySignalsObservable, LazySignalsState },
+        prelude::*,
+        testing::*,
+    };
+
+    fn subscribers(state: Signal<u32>, app: &mut App) -> Vec<Entity> {
+        let mut state = app.world_mut().get_mut::<LazySignalsState<u32>>(state.entity()).unwrap();
+        state.merge_subscribers();
+        state.get_subscribers()
+    }
+
+    // a state with a memo and an effect downstream of it
+    fn chain(app: &mut App) -> (Signal<u32>, Memo<u32>, EffectHandle) {
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let state = LazySignals.state::<u32>(1, &mut commands);
+        let memo = LazySignals.computed::<(Option<u32>,), u32>(
+            |(value,)| LazySignals::result(value.unwrap_or(0) * 2),
+            (state,),
+            &mut commands
+        );
+        let effect = LazySignals.effect::<(Option<u32>,)>(
+            |_, _| None,
+            (memo,),
+            vec![],
+            &mut commands
+        );
+        update(app);
+        (state, memo, effect)
+    }
+
+    #[test]
+    fn dependents_of_a_despawned_source_are_faulted() {
+        let mut app = app();
+        let (state, memo, _) = chain(&mut app);
+        let effect = LazySignals.effect::<(Option<u32>,)>(
+            |_, _| None,
+            (state,),
+            vec![],
+            &mut app.world_mut().commands()
+        );
+        update(&mut app);
+
+        app.world_mut().despawn(state.entity());
+        update(&mut app);
+
+        assert_eq!(
+            LazySignals.get_error::<u32>(memo, app.world()),
+            Some(LazySignalsError::NoSignalError)
+        );
+        assert_eq!(
+            app.world().get::<Faulted>(effect.entity()).map(|faulted| faulted.error.clone()),
+            Some(LazySignalsError::NoSignalError)
+        );
+    }
+
+    #[test]
+    fn despawned_subscribers_are_pruned() {
+        let mut app = app();
+        let (state, memo, _) = chain(&mut app);
+        assert!(subscribers(state, &mut app).contains(&memo.entity()));
+
+        app.world_mut().despawn(memo.entity());
+        update(&mut app);
+
+        assert!(!subscribers(state, &mut app).contains(&memo.entity()));
+    }
+
+    #[test]
+    fn owned_dependents_are_despawned_along_with_their_sources() {
+        let mut app = app();
+        let (state, memo, effect) = chain(&mut app);
+        app.world_mut().entity_mut(memo.entity()).insert(OwnedBySources);
+        app.world_mut().entity_mut(effect.entity()).insert(OwnedBySources);
+
+        app.world_mut().despawn(state.entity());
+        update(&mut app);
+
+        assert!(app.world().get_entity(memo.entity()).is_none());
+        assert!(app.world().get_entity(effect.entity()).is_none());
+    }
+
+    #[test]
+    fn effect_systems_are_removed_along_with_their_effects() {
+        let mut app = app();
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let state = LazySignals.state::<u32>(1, &mut commands);
+        let effect = LazySignals.effect_system::<(Option<u32>,), _>(
+            |In(_): In<(Option<u32>,)>| {},
+            (state,),
+            vec![],
+            &mut commands
+        );
+        update(&mut app);
+
+        let mut query = app.world_mut().query::<(Entity, &OwnedByEffect)>();
+        let system = query.single(app.world()).0;
+
+        app.world_mut().despawn(effect.entity());
+        update(&mut app);
+
+        assert!(app.world().get_entity(system).is_none());
+    }
+}
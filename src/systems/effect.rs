@@ -1,36 +1,200 @@
 use bevy::{
-    ecs::{ system::BoxedSystem, world::CommandQueue },
+    core::FrameCount,
+    ecs::{ storage::SparseSet, system::{ BoxedSystem, SystemId } },
     prelude::*,
     reflect::DynamicTuple,
-    tasks::{ block_on, futures_lite::future, Task },
 };
 
-use crate::{ arcane_wizardry::*, framework::* };
+use crate::{ arcane_wizardry::*, commands::SendSignalCommand, framework::* };
 
-type DeferredEffectsParam = (With<DeferredEffect>, Without<RunningTask>);
+type DeferredEffectsParam = (With<DeferredEffect>, Without<Faulted>);
 
-// get all the currently running tasks
-pub fn check_tasks(mut running_tasks: Query<(Entity, &mut RunningTask)>, mut commands: Commands) {
-    for (entity, mut running) in running_tasks.iter_mut() {
-        if let Some(mut commands_queue) = block_on(future::poll_once(&mut running.task)) {
-            // append the returned command queue to have it execute later
-            commands.append(&mut commands_queue);
+type EffectParam<'a> = (Entity, &'a LazyEffect, Option<&'a Triggered>);
 
-            if let Some(mut entity) = commands.get_entity(entity) {
-                entity.remove::<RunningTask>();
+type RunningTaskParam<'a> = (
+    Entity,
+    &'a mut RunningTask,
+    &'a LazyEffect,
+    Option<&'a ActionOptions>,
+    Option<&'a LazyResource>,
+);
+
+// apply the tasks that completed since last time, then enforce the timeouts and start the retries
+pub fn check_tasks(
+    mut running_tasks: Query<RunningTaskParam>,
+    deadlines: Query<Entity, With<TaskDeadline>>,
+    completions: Res<LazySignalsCompletions>,
+    time: Option<Res<Time>>,
+    mut commands: Commands
+) {
+    let now = time.map_or(0.0, |time| time.elapsed_seconds());
+    let mut failures = Vec::<(Entity, LazySignalsError)>::new();
+    let mut finished = empty_set();
+
+    // only the tasks that actually completed are looked at here
+    for completion in completions.drain() {
+        let entity = completion.action;
+        let Ok((_, mut running, _, options, _)) = running_tasks.get_mut(entity) else {
+            continue;
+        };
+
+        // a task that was cancelled (e.g. by `ActionConcurrency::Switch`) may have completed first
+        let Some(index) = running.tasks.iter().position(|task| task.id == completion.id) else {
+            continue;
+        };
+
+        match completion.result {
+            Ok(mut commands_queue) => {
+                // append the returned command queue to have it execute later
+                commands.append(&mut commands_queue);
+                running.tasks.remove(index);
+            }
+            Err(error) => {
+                let options = options.cloned().unwrap_or_default();
+                if !retry_later(&mut running.tasks[index], &options, now) {
+                    running.tasks.remove(index);
+                    failures.push((entity, error));
+                }
+            }
+        }
+        finished.insert(entity, ());
+    }
+
+    // the tasks that are still in flight are only checked against the clock if they have a deadline
+    // (the others are not looked at again until they complete)
+    for entity in deadlines.iter() {
+        let Ok((_, mut running, effect, options, _)) = running_tasks.get_mut(entity) else {
+            continue;
+        };
+        let (Some(options), EffectContext::Long(function)) = (options, &effect.function) else {
+            continue;
+        };
+
+        running.tasks.retain_mut(|task| {
+            if task.task.is_none() {
+                // start a retry once it has waited long enough
+                if now >= task.start {
+                    trace!("Retrying task {:?} (attempt {})", entity, task.attempt);
+                    let future = function.lock().unwrap()(&task.args);
+                    let (id, spawned) = completions.spawn(entity, future);
+                    task.id = id;
+                    task.task = Some(spawned);
+                    task.start = now;
+                }
+                return true;
+            }
+
+            match options.timeout {
+                Some(timeout) if now - task.start >= timeout => {
+                    if retry_later(task, options, now) {
+                        return true;
+                    }
+                    failures.push((entity, LazySignalsError::Timeout(timeout)));
+                    finished.insert(entity, ());
+                    false
+                }
+                _ => true,
+            }
+        });
+    }
+
+    // report before any queued run starts, so a resource ends up `Pending` again
+    for (entity, error) in failures {
+        debug!("Task {:?} failed: {}", entity, error);
+        let Ok((_, _, _, options, resource)) = running_tasks.get(entity) else {
+            continue;
+        };
+        if let Some(signal) = options.and_then(|options| options.failures) {
+            commands.add(SendSignalCommand { signal: signal.entity(), data: error.clone() });
+        }
+        if let Some(resource) = resource {
+            let failed = resource.failed;
+            commands.add(move |world: &mut World| failed(entity, error, world));
+        }
+    }
+
+    for entity in finished.indices() {
+        let Ok((_, mut running, effect, _, resource)) = running_tasks.get_mut(entity) else {
+            continue;
+        };
+        if !running.tasks.is_empty() {
+            continue;
+        }
+
+        // run again with the args that were queued while the last task was running
+        let queued = running.queued.take();
+        if let (Some(args), EffectContext::Long(function)) = (queued, &effect.function) {
+            trace!("Running queued task {:?}", entity);
+            let (id, task) = completions.spawn(entity, function.lock().unwrap()(&args));
+            running.tasks.push(ActionTask::new(id, task, args, now));
+
+            // after the result of the last task, which was appended above
+            if let Some(resource) = resource {
+                let pending = resource.pending;
+                commands.add(move |world: &mut World| pending(entity, world));
             }
+            continue;
+        }
+
+        commands.entity(entity).remove::<RunningTask>();
+    }
+
+    // a task may have started waiting for a retry, or the last one with a deadline may be done
+    for entity in finished.indices().chain(deadlines.iter()) {
+        let has_deadline = running_tasks
+            .get(entity)
+            .is_ok_and(|(_, running, _, options, _)| running.has_deadline(options));
+        if has_deadline != deadlines.contains(entity) {
+            if has_deadline {
+                commands.entity(entity).insert(TaskDeadline);
+            } else {
+                commands.entity(entity).remove::<TaskDeadline>();
+            }
+        }
+    }
+}
+
+// drop the task of a failed run and schedule another attempt, if the retry policy allows it
+fn retry_later(task: &mut ActionTask, options: &ActionOptions, now: f32) -> bool {
+    // dropping the task cancels it if it is still running
+    task.task = None;
+    match options.retry {
+        Some(retry) if task.attempt < retry.max_attempts => {
+            task.start = now + retry.backoff(task.attempt);
+            task.attempt += 1;
+            true
         }
+        _ => false,
     }
 }
 
+// the given signals that have been sent but not yet applied
+fn sent_signals(signals: &[Entity], world: &World) -> Vec<Entity> {
+    signals
+        .iter()
+        .filter(|signal| world.get::<SendSignal>(**signal).is_some())
+        .copied()
+        .collect()
+}
+
 // run all the effects what need running
+// the world is flushed after each effect (to catch an effect sending its own sources), so the
+// commands an effect queues are applied before the next effect runs instead of after all of them
 pub fn apply_deferred_effects(
     world: &mut World,
     query_changed: &mut QueryState<(Entity,), With<ValueChanged>>,
-    query_effects: &mut QueryState<(Entity, &LazyEffect, Option<&Triggered>), DeferredEffectsParam>
+    query_effects: &mut QueryState<EffectParam, DeferredEffectsParam>,
+    mut component_id_set: Local<ComponentIdSet>
 ) {
     trace!("EFFECTS");
 
+    let config = world.get_resource::<LazySignalsConfig>().cloned().unwrap_or_default();
+    let frame = world.get_resource::<FrameCount>().map(|frame_count| frame_count.0);
+    let now = world.get_resource::<Time>().map_or(0.0, |time| time.elapsed_seconds());
+    let mut budget = world.get_resource_or_insert_with(LazySignalsBudget::default);
+    budget.start_frame(frame);
+    budget.next_pass();
+
     // build a set of changed Computeds and Signals
     let mut changed = empty_set();
     query_changed.iter(world).for_each(|(entity,)| {
@@ -38,10 +202,11 @@ pub fn apply_deferred_effects(
     });
 
     // store newly created Tasks here
-    let mut new_tasks = Vec::<(Entity, Task<CommandQueue>)>::new();
+    let mut new_tasks = Vec::<(Entity, ActionTask)>::new();
 
     // collapse the query or get world concurrency errors
     let mut relationships = EntityRelationshipSet::new();
+    let mut args_modes = SparseSet::<Entity, ArgsMode>::new();
     let mut triggered = empty_set();
     query_effects.iter(world).for_each(|(entity, effect, triggered_effect)| {
         // only add the effect if it isn't already running
@@ -49,6 +214,7 @@ pub fn apply_deferred_effects(
         deps.append(&mut effect.sources.clone());
         deps.append(&mut effect.triggers.clone());
         relationships.insert(entity, deps);
+        args_modes.insert(entity, effect.args_mode);
         if triggered_effect.is_some() {
             triggered.insert(entity, ());
         }
@@ -79,12 +245,38 @@ pub fn apply_deferred_effects(
             }
         }
 
-        let mut entity = world.entity_mut(effect);
+        // guard against feedback loops, whether they happen within a frame or across frames
         if actually_run {
-            effects.insert(effect, ());
+            let mut budget = world.resource_mut::<LazySignalsBudget>();
+            let runs = budget.count_effect_run(effect).max(budget.self_send_streak(effect) + 1);
+            if runs > config.max_effect_runs {
+                // only report the first time the limit is hit each frame (or each loop)
+                if runs == config.max_effect_runs + 1 {
+                    let mut chain = sources
+                        .iter()
+                        .filter(|source| changed.contains(**source))
+                        .copied()
+                        .collect::<Vec<_>>();
+                    chain.push(effect);
+                    report_loop(LazySignalsLimit::EffectRuns, chain, config.cut_off, world);
+                }
+
+                if config.cut_off {
+                    actually_run = false;
+                }
+            }
+        }
+
+        // an effect that ran earlier in this loop may have despawned this one
+        let Some(mut entity) = world.get_entity_mut(effect) else {
+            continue;
+        };
+
+        // remove TriggeredEffect so we don't run this again next frame (even if it was cut off)
+        entity.remove::<Triggered>();
 
-            // remove TriggeredEffect so we don't run this again next frame
-            entity.remove::<Triggered>();
+        if actually_run {
+            effects.insert(effect, ());
         }
 
         // remove the DeferredEffect component
@@ -92,10 +284,9 @@ pub fn apply_deferred_effects(
 
         // make sure if effects are deferred but not run that they still refresh
         // otherwise they will not be notified next time
-        world.resource_scope(|world, type_registry: Mut<AppTypeRegistry>| {
-            let type_registry = type_registry.read();
+        world.resource_scope(|world, registry: Mut<LazySignalsRegistry>| {
             for source in sources {
-                subscribe(&effect, source, &type_registry, world);
+                subscribe(&effect, source, &registry, world);
             }
         });
     }
@@ -105,33 +296,41 @@ pub fn apply_deferred_effects(
         let sources = relationships.get(effect).map_or(Vec::<Entity>::new(), |s| s.to_vec());
         trace!("-found effect with sources {:#?}", sources);
 
-        // add the source component ID to the set (probably could be optimized)
-        let mut component_id_set = ComponentIdSet::new();
-        let mut component_info_set = ComponentInfoSet::new();
+        // add the source component ID to the set (the buffer is kept between effects and runs)
+        component_id_set.clear();
 
-        // build component id -> info map
+        // a source (or the effect itself) may have been despawned by an effect that already ran
+        if world.get_entity(effect).is_none() {
+            continue;
+        }
+        if !sources.iter().all(|source| is_observable(source, world)) {
+            world.resource_scope(|world, registry: Mut<LazySignalsRegistry>| {
+                fault_derived(&effect, LazySignalsError::NoSignalError, &registry, world);
+            });
+            continue;
+        }
+
+        // build entity -> component id map
         for source in sources.iter() {
             let immutable = world.entity(*source).get::<ImmutableState>().unwrap();
             let component_id = immutable.component_id;
             trace!("-found an effect source with component ID {:#?}", component_id);
             component_id_set.insert(*source, component_id);
-            if let Some(info) = world.components().get_info(component_id) {
-                component_info_set.insert(component_id, info.clone());
-            }
         }
 
-        world.resource_scope(|world, type_registry: Mut<AppTypeRegistry>| {
-            let type_registry = type_registry.read();
+        // note which sources were already sent, so sending them from the effect can be caught
+        let sent = sent_signals(&sources, world);
 
+        world.resource_scope(|world, registry: Mut<LazySignalsRegistry>| {
             // prepare the args
+            let args_mode = *args_modes.get(effect).unwrap();
             let mut args = DynamicTuple::default();
             for source in sources.iter() {
                 let component_id = component_id_set.get(*source).unwrap();
-                let type_id = component_info_set.get(*component_id).unwrap().type_id().unwrap();
 
-                // call the copy_data method via reflection
+                // call the copy_data method through the registered vtable
                 // this will append the source data to the args tuple
-                // FIXME indicate an error if the args don't line up?
+                // (the args were checked against the sources when this was created)
                 if let Some(mut source) = world.get_entity_mut(*source) {
                     // insert arcane wizardry here
                     run_as_observable(
@@ -139,10 +338,16 @@ pub fn apply_deferred_effects(
                         Some(&mut args),
                         Some(&effect),
                         component_id,
-                        &type_id,
-                        &type_registry,
+                        &registry,
                         Box::new(|observable, args, target| {
-                            observable.copy_data(*target.unwrap(), args.unwrap());
+                            match args_mode {
+                                ArgsMode::Options => {
+                                    observable.copy_data(*target.unwrap(), args.unwrap());
+                                }
+                                ArgsMode::Results => {
+                                    observable.copy_result(*target.unwrap(), args.unwrap());
+                                }
+                            }
                             None
                         })
                     );
@@ -151,6 +356,7 @@ pub fn apply_deferred_effects(
 
             // actually run the effect
             let mut effect_system = Option::<BoxedSystem>::None;
+            let mut registered_system = Option::<SystemId<DynamicTuple>>::None;
             let mut new_task = false;
 
             // drop the UnsafeWorldCell after this block so we can access the real world again
@@ -166,34 +372,81 @@ pub fn apply_deferred_effects(
                         match function {
                             EffectContext::Short(effect) => {
                                 // I think this world must not be used to mutate the effect, not sure
-                                effect_system = effect.lock().unwrap()(&args, world.world_mut());
+                                let args = std::mem::take(&mut args);
+                                effect_system = effect.lock().unwrap()(args, world.world_mut());
                             }
                             EffectContext::Long(_) => {
                                 trace!("Running task {:?}", effect);
                                 new_task = true;
                             }
+                            EffectContext::System(system_id) => {
+                                registered_system = Some(*system_id);
+                            }
                         }
                     }
                 }
+            }
 
-                // run and mark the new task
-                if new_task {
-                    let handle = world.get_entity(effect).unwrap();
-                    unsafe {
-                        let lazy_effect = handle.get::<LazyEffect>().unwrap();
-                        let function = &lazy_effect.function;
-                        if let EffectContext::Long(function) = function {
-                            let task = function.lock().unwrap()(&args);
-                            new_tasks.push((effect, task));
-                        }
+            // run and mark the new task, unless the action says otherwise while one is running
+            if new_task {
+                let concurrency = world
+                    .get::<ActionOptions>(effect)
+                    .map_or(ActionConcurrency::default(), |options| options.concurrency);
+                let running = world
+                    .get::<RunningTask>(effect)
+                    .map_or(0, |running| running.tasks.len());
+
+                let start = match concurrency {
+                    _ if running == 0 => true,
+                    ActionConcurrency::Exhaust => false,
+                    ActionConcurrency::Switch => {
+                        // dropping a task cancels it
+                        trace!("-switching task {:?}", effect);
+                        world.entity_mut(effect).remove::<(RunningTask, TaskDeadline)>();
+                        true
+                    }
+                    ActionConcurrency::Queue => {
+                        // only the latest args are kept
+                        trace!("-queueing task {:?}", effect);
+                        let mut running = world.get_mut::<RunningTask>(effect).unwrap();
+                        running.queued = Some(std::mem::take(&mut args));
+                        false
                     }
+                    ActionConcurrency::Merge(limit) => running < limit,
+                };
+
+                if start {
+                    if
+                        let Some(LazyEffect { function: EffectContext::Long(function), .. }) =
+                            world.get::<LazyEffect>(effect)
+                    {
+                        let future = function.lock().unwrap()(&args);
+                        let (id, task) = world
+                            .get_resource_or_insert_with(LazySignalsCompletions::default)
+                            .spawn(effect, future);
+                        let args = std::mem::take(&mut args);
+                        new_tasks.push((effect, ActionTask::new(id, task, args, now)));
+                    }
+                    if let Some(resource) = world.get::<LazyResource>(effect) {
+                        (resource.pending)(effect, world);
+                    }
+                } else if concurrency != ActionConcurrency::Queue {
+                    debug!("{:?} is still running, so it will not run again this time", effect);
+                }
+            }
+
+            // run the registered system with the args
+            if let Some(system_id) = registered_system {
+                trace!("Running effect system {:?}", system_id);
+                if let Err(error) = world.run_system_with_input(system_id, args) {
+                    error!("error running effect system: {}", error);
                 }
             }
 
             // run the effect system
             if let Some(effect_system) = effect_system {
-                // FIXME this seems horribly inefficient
-                // is there a way
+                // a returned system only runs once, so it is thrown away afterwards
+                // (use `effect_system` instead to register one that keeps its state)
                 let id = world.register_boxed_system(effect_system);
                 match world.run_system(id) {
                     Ok(_) => {}
@@ -202,10 +455,404 @@ pub fn apply_deferred_effects(
                 world.despawn(id.entity());
             }
         });
+
+        // an effect that sends its own sources will run again the next time around
+        world.flush();
+        if sent_signals(&sources, world).len() > sent.len() {
+            world.resource_mut::<LazySignalsBudget>().count_self_send(effect);
+        }
     }
 
     // mark the new tasks as running
-    for task in new_tasks.drain(..) {
-        world.entity_mut(task.0).insert(RunningTask { task: task.1 });
+    for (effect, task) in new_tasks.drain(..) {
+        if let Some(mut entity) = world.get_entity_mut(effect) {
+            match entity.get_mut::<RunningTask>() {
+                Some(mut running) => running.tasks.push(task),
+                None => {
+                    entity.insert(RunningTask::new(task));
+                }
+            }
+
+            // a task that can time out has to be checked against the clock
+            if entity.get::<ActionOptions>().is_some_and(|options| options.timeout.is_some()) {
+                entity.insert(TaskDeadline);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ sync::{ atomic::{ AtomicBool, Ordering }, Arc, Mutex }, time::Duration };
+
+    use bevy::{
+        ecs::world::CommandQueue,
+        prelude::*,
+        tasks::{ futures_lite::future::{ pending, yield_now }, AsyncComputeTaskPool },
+    };
+
+    use crate::{ prelude::*, testing::* };
+
+    // the args of each task of an action that finished
+    #[derive(Resource, Default)]
+    struct Finished(Vec<u32>);
+
+    // start an action with the given concurrency and send each value to its source while the first
+    // task is held up, then let every task finish and return the args of those that started and
+    // of those that finished
+    fn run_concurrently(concurrency: ActionConcurrency, sends: &[u32]) -> (Vec<u32>, Vec<u32>) {
+        let mut app = app();
+        app.init_resource::<Finished>();
+        let gate = Arc::new(AtomicBool::new(false));
+        let started = Arc::new(Mutex::new(Vec::<u32>::new()));
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let source = LazySignals.state::<u32>(0, &mut commands);
+        let (task_gate, task_started) = (gate.clone(), started.clone());
+        LazySignals.action_with_options::<(Option<u32>,)>(
+            move |(value,)| {
+                let value = value.unwrap();
+                task_started.lock().unwrap().push(value);
+                let gate = task_gate.clone();
+                AsyncComputeTaskPool::get().spawn(async move {
+                    while !gate.load(Ordering::SeqCst) {
+                        yield_now().await;
+                    }
+                    let mut queue = CommandQueue::default();
+                    queue.push(move |world: &mut World| {
+                        world.resource_mut::<Finished>().0.push(value);
+                    });
+                    queue
+                })
+            },
+            (source,),
+            vec![],
+            ActionOptions { concurrency, ..default() },
+            &mut commands
+        );
+        update(&mut app);
+
+        for value in sends {
+            LazySignals.send(source, *value, &mut app.world_mut().commands());
+            update(&mut app);
+        }
+        gate.store(true, Ordering::SeqCst);
+        update_for(&mut app, Duration::from_millis(200));
+
+        let started = started.lock().unwrap().clone();
+        let mut finished = app.world().resource::<Finished>().0.clone();
+        finished.sort();
+        (started, finished)
+    }
+
+    // the status of a resource that loads ten times its source (or fails for 13) before anything is
+    // sent, then every status it goes through while each load is held up until all of the values
+    // were sent
+    fn load_resource(sends: &[u32]) -> (Option<AsyncStatus<u32>>, Vec<AsyncStatus<u32>>) {
+        let mut app = app();
+        let gate = Arc::new(AtomicBool::new(false));
+        let statuses = Arc::new(Mutex::new(Vec::<AsyncStatus<u32>>::new()));
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let source = LazySignals.state::<u32>(0, &mut commands);
+        let load_gate = gate.clone();
+        let resource = LazySignals.resource::<(Option<u32>,), u32>(
+            move |(value,): (Option<u32>,)| {
+                let gate = load_gate.clone();
+                async move {
+                    while !gate.load(Ordering::SeqCst) {
+                        yield_now().await;
+                    }
+                    match value.unwrap() {
+                        13 => Err(LazySignalsError::user("unlucky")),
+                        value => Ok(value * 10),
+                    }
+                }
+            },
+            (source,),
+            &mut commands
+        );
+        let seen = statuses.clone();
+        LazySignals.effect::<(Option<AsyncStatus<u32>>,)>(
+            move |(status,), _world| {
+                seen.lock().unwrap().push(status.unwrap());
+                None
+            },
+            (resource,),
+            vec![],
+            &mut commands
+        );
+        update(&mut app);
+        let idle = LazySignals.read::<AsyncStatus<u32>>(resource, app.world());
+
+        for value in sends {
+            LazySignals.send(source, *value, &mut app.world_mut().commands());
+            update(&mut app);
+        }
+        gate.store(true, Ordering::SeqCst);
+        update_for(&mut app, Duration::from_millis(200));
+
+        let statuses = statuses.lock().unwrap().clone();
+        (idle, statuses)
+    }
+
+    // run a fallible action that fails the given number of times before it succeeds, and return how
+    // many times it ran along with whatever was sent to its failures signal
+    fn run_flaky(failures: u32, retry: ActionRetry) -> (u32, Vec<u32>, Option<LazySignalsError>) {
+        let mut app = app();
+        app.init_resource::<Finished>();
+        let runs = Arc::new(Mutex::new(0));
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let source = LazySignals.state::<u32>(0, &mut commands);
+        let failed = LazySignals.state::<LazySignalsError>(
+            LazySignalsError::NoNextValue,
+            &mut commands
+        );
+        let task_runs = runs.clone();
+        LazySignals.action_fallible::<(Option<u32>,)>(
+            move |(value,)| {
+                let value = value.unwrap();
+                let mut runs = task_runs.lock().unwrap();
+                *runs += 1;
+                let fail = *runs <= failures;
+                AsyncComputeTaskPool::get().spawn(async move {
+                    if fail {
+                        return Err(LazySignalsError::user("flaky"));
+                    }
+                    let mut queue = CommandQueue::default();
+                    queue.push(move |world: &mut World| {
+                        world.resource_mut::<Finished>().0.push(value);
+                    });
+                    Ok(queue)
+                })
+            },
+            (source,),
+            vec![],
+            ActionOptions { retry: Some(retry), failures: Some(failed), ..default() },
+            &mut commands
+        );
+        LazySignals.send(source, 5, &mut commands);
+        update_for(&mut app, Duration::from_millis(200));
+
+        let runs = *runs.lock().unwrap();
+        let finished = app.world().resource::<Finished>().0.clone();
+        (runs, finished, LazySignals.read::<LazySignalsError>(failed, app.world()))
+    }
+
+    #[test]
+    fn retry_runs_again_until_it_succeeds() {
+        let retry = ActionRetry { max_attempts: 3, delay: 0.01, factor: 2.0 };
+        assert_eq!(run_flaky(2, retry), (3, vec![5], Some(LazySignalsError::NoNextValue)));
+    }
+
+    #[test]
+    fn retry_gives_up_after_the_last_attempt() {
+        let retry = ActionRetry { max_attempts: 2, delay: 0.01, factor: 2.0 };
+        assert_eq!(run_flaky(5, retry), (2, vec![], Some(LazySignalsError::user("flaky"))));
+    }
+
+    #[test]
+    fn timeout_cancels_the_task_and_reports_it() {
+        let mut app = app();
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let source = LazySignals.state::<u32>(0, &mut commands);
+        let failed = LazySignals.state::<LazySignalsError>(
+            LazySignalsError::NoNextValue,
+            &mut commands
+        );
+        let action = LazySignals.action_with_options::<(Option<u32>,)>(
+            |_| AsyncComputeTaskPool::get().spawn(pending()),
+            (source,),
+            vec![],
+            ActionOptions { timeout: Some(0.05), failures: Some(failed), ..default() },
+            &mut commands
+        );
+        LazySignals.send(source, 5, &mut commands);
+        update(&mut app);
+        assert!(app.world().get::<RunningTask>(action.entity()).is_some());
+        assert!(app.world().get::<TaskDeadline>(action.entity()).is_some());
+
+        update_for(&mut app, Duration::from_millis(200));
+        assert_eq!(
+            LazySignals.read::<LazySignalsError>(failed, app.world()),
+            Some(LazySignalsError::Timeout(0.05))
+        );
+        assert!(app.world().get::<RunningTask>(action.entity()).is_none());
+        assert!(app.world().get::<TaskDeadline>(action.entity()).is_none());
+    }
+
+    #[test]
+    fn only_completed_tasks_are_applied() {
+        let mut app = app();
+        app.init_resource::<Finished>();
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let source = LazySignals.state::<u32>(0, &mut commands);
+        let done = LazySignals.action::<(Option<u32>,)>(
+            |(value,)| {
+                let value = value.unwrap();
+                AsyncComputeTaskPool::get().spawn(async move {
+                    let mut queue = CommandQueue::default();
+                    queue.push(move |world: &mut World| {
+                        world.resource_mut::<Finished>().0.push(value);
+                    });
+                    queue
+                })
+            },
+            (source,),
+            vec![],
+            &mut commands
+        );
+        let stuck = LazySignals.action::<(Option<u32>,)>(
+            |_| AsyncComputeTaskPool::get().spawn(pending()),
+            (source,),
+            vec![],
+            &mut commands
+        );
+        LazySignals.send(source, 5, &mut commands);
+        update_for(&mut app, Duration::from_millis(100));
+
+        assert_eq!(app.world().resource::<Finished>().0, vec![5]);
+        assert!(app.world().get::<RunningTask>(done.entity()).is_none());
+        let running = app.world().get::<RunningTask>(stuck.entity()).unwrap();
+        assert_eq!(running.tasks.len(), 1);
+
+        // without a timeout, it is not checked against the clock while it waits
+        assert!(app.world().get::<TaskDeadline>(stuck.entity()).is_none());
+    }
+
+    #[test]
+    fn resource_goes_from_idle_to_pending_to_ready() {
+        assert_eq!(
+            load_resource(&[1]),
+            (Some(AsyncStatus::Idle), vec![AsyncStatus::Pending, AsyncStatus::Ready(10)])
+        );
+    }
+
+    #[test]
+    fn resource_reports_a_failed_load() {
+        let failed = AsyncStatus::Failed(LazySignalsError::user("unlucky"));
+        assert_eq!(load_resource(&[13]).1, vec![AsyncStatus::Pending, failed]);
+    }
+
+    #[test]
+    fn resource_switches_to_the_latest_load() {
+        // the stale load is cancelled, so it never becomes `Ready(10)`
+        assert_eq!(load_resource(&[1, 2]).1, vec![AsyncStatus::Pending, AsyncStatus::Ready(20)]);
+    }
+
+    #[test]
+    fn effect_system_keeps_its_state_until_it_is_disposed() {
+        let mut app = app();
+        app.init_resource::<Finished>();
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let source = LazySignals.state::<u32>(0, &mut commands);
+        let effect = LazySignals.effect_system::<(Option<u32>,), _>(
+            |In(_): In<(Option<u32>,)>, mut runs: Local<u32>, mut finished: ResMut<Finished>| {
+                *runs += 1;
+                finished.0.push(*runs);
+            },
+            (source,),
+            vec![],
+            &mut commands
+        );
+        update(&mut app);
+
+        for value in [1, 2, 3] {
+            LazySignals.send(source, value, &mut app.world_mut().commands());
+            update(&mut app);
+        }
+        assert_eq!(app.world().resource::<Finished>().0, vec![1, 2, 3]);
+
+        let Some(LazyEffect { function: EffectContext::System(system_id), .. }) = app
+            .world()
+            .get::<LazyEffect>(effect.entity()) else {
+            panic!("not an effect system");
+        };
+        let system = system_id.entity();
+        app.world_mut().despawn(effect.entity());
+        update(&mut app);
+        assert!(app.world().get_entity(system).is_none());
+    }
+
+    #[test]
+    fn exhaust_ignores_runs_while_busy() {
+        assert_eq!(run_concurrently(ActionConcurrency::Exhaust, &[1, 2]), (vec![1], vec![1]));
+    }
+
+    #[test]
+    fn switch_cancels_the_running_task() {
+        assert_eq!(run_concurrently(ActionConcurrency::Switch, &[1, 2]), (vec![1, 2], vec![2]));
+    }
+
+    #[test]
+    fn queue_runs_the_latest_args_afterwards() {
+        assert_eq!(
+            run_concurrently(ActionConcurrency::Queue, &[1, 2, 3]),
+            (vec![1, 3], vec![1, 3])
+        );
+    }
+
+    #[test]
+    fn merge_runs_up_to_the_limit() {
+        assert_eq!(
+            run_concurrently(ActionConcurrency::Merge(2), &[1, 2, 3]),
+            (vec![1, 2], vec![1, 2])
+        );
+    }
+
+    // an effect that sends its own source, which makes it run again every frame
+    fn self_feeding(config: LazySignalsConfig) -> (Option<u32>, Vec<LazySignalsLoopDetected>) {
+        let mut app = app();
+        app.insert_resource(config);
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let x = LazySignals.state::<u32>(0, &mut commands);
+        let effect = LazySignals.effect::<(Option<u32>,)>(
+            move |(value,), world| {
+                LazySignals.send(x, value.unwrap() + 1, &mut world.commands());
+                None
+            },
+            (x,),
+            vec![],
+            &mut commands
+        );
+        LazySignals.send(x, 1, &mut commands);
+
+        let mut loops = Vec::new();
+        for _ in 0..100 {
+            update(&mut app);
+            loops.append(&mut loops_detected(&mut app));
+        }
+        assert!(loops.iter().all(|event| event.chain == vec![x.entity(), effect.entity()]));
+        (LazySignals.read::<u32>(x, app.world()), loops)
+    }
+
+    #[test]
+    fn self_feeding_effect_is_reported() {
+        let (value, loops) = self_feeding(LazySignalsConfig::default());
+        assert_eq!(value, Some(100));
+        assert_eq!(loops.len(), 1);
+        assert!(!loops[0].cut_off);
+    }
+
+    #[test]
+    fn self_feeding_effect_is_cut_off() {
+        let config = LazySignalsConfig { cut_off: true, ..default() };
+        let max_effect_runs = config.max_effect_runs as u32;
+        let (value, loops) = self_feeding(config);
+        assert_eq!(value, Some(max_effect_runs + 1));
+        assert_eq!(loops.len(), 1);
+        assert!(loops[0].cut_off);
     }
 }
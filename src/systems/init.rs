@@ -1,13 +1,14 @@
-use bevy::{ ecs::world::World, prelude::* };
+use bevy::{ ecs::{ storage::SparseSet, world::World }, prelude::* };
 
-use crate::{ arcane_wizardry::*, framework::* };
+use crate::{ arcane_wizardry::*, framework::*, graph::{ find_cycle, find_height } };
 
 type DerivedParam<'a> = (Entity, Option<&'a ComputedImmutable>, Option<&'a LazyEffect>);
 // remove ValueChanged components
 pub fn init_lazy_signals(
     world: &mut World,
     query_deriveds: &mut QueryState<DerivedParam, With<InitDependencies>>,
-    query_value_changed: &mut QueryState<Entity, With<ValueChanged>>
+    query_value_changed: &mut QueryState<Entity, With<ValueChanged>>,
+    query_triggered: &mut QueryState<Entity, (With<Triggered>, Without<LazyEffect>)>
 ) {
     // reset the internal change tracking
     let mut changed = empty_set();
@@ -18,13 +19,25 @@ pub fn init_lazy_signals(
         world.entity_mut(*entity).remove::<ValueChanged>();
     }
 
+    // signals are only marked triggered until the next batch (effects clear their own when they run)
+    let triggered = query_triggered.iter(world).collect::<Vec<_>>();
+    for entity in triggered {
+        world.entity_mut(entity).remove::<Triggered>();
+    }
+
     // build the branches of the subscriber trees
     // FIXME should we actually just compute and trigger everything that is marked instead of faking it?
     let mut relationships = EntityRelationshipSet::new();
+    let mut cycles = Vec::<(Entity, Vec<Entity>)>::new();
 
     query_deriveds.iter(world).for_each(|(entity, computed, effect)| {
         let mut subs = Vec::<Entity>::new();
         if let Some(computed) = computed {
+            // a computed that ends up depending on itself would never finish computing
+            if let Some(path) = find_cycle(entity, &computed.sources, world) {
+                cycles.push((entity, path));
+                return;
+            }
             subs.append(&mut computed.sources.clone());
         }
         if let Some(effect) = effect {
@@ -35,16 +48,65 @@ pub fn init_lazy_signals(
     });
 
     // run the subscribe method on all sources and triggers
-    world.resource_scope(|world, type_registry: Mut<AppTypeRegistry>| {
-        let type_registry = type_registry.read();
+    world.resource_scope(|world, registry: Mut<LazySignalsRegistry>| {
+        // reject anything that closes a cycle instead of subscribing it
+        for (entity, path) in cycles {
+            let error = LazySignalsError::Cycle(path);
+            error!("{:?} will not run: {}", entity, error);
+            fault_derived(&entity, error, &registry, world);
+            world.entity_mut(entity).remove::<InitDependencies>();
+        }
+
+        // rank everything first, while InitDependencies still says which cached ranks are stale
+        let mut heights = SparseSet::<Entity, u32>::new();
+        for (entity, _) in relationships.iter() {
+            find_height(*entity, world, &mut heights);
+        }
+
         for (entity, subs) in relationships.iter() {
             // loop through the sources
             for source in subs.iter() {
-                subscribe(entity, source, &type_registry, world);
+                subscribe(entity, source, &registry, world);
             }
 
             // mark as processed
-            world.get_entity_mut(*entity).unwrap().remove::<InitDependencies>();
+            let height = *heights.get(*entity).unwrap();
+            world
+                .get_entity_mut(*entity)
+                .unwrap()
+                .insert(Rank { height })
+                .remove::<InitDependencies>();
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{ prelude::*, testing::* };
+
+    #[test]
+    fn computed_that_reads_itself_is_rejected() {
+        let mut app = app();
+
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let a = LazySignals.state::<u32>(1, &mut commands);
+        let memo = LazySignals.computed::<(Option<u32>,), u32>(
+            |(a,)| LazySignals::result(a.unwrap_or(0) + 1),
+            (a,),
+            &mut commands
+        );
+        update(&mut app);
+
+        // wire it to itself behind the back of the commands, which would refuse
+        let mut entity = app.world_mut().entity_mut(memo.entity());
+        entity.get_mut::<ComputedImmutable>().unwrap().sources = vec![memo.entity()];
+        entity.insert(InitDependencies);
+        update(&mut app);
+
+        assert_eq!(
+            LazySignals.get_error::<u32>(memo, app.world()),
+            Some(LazySignalsError::Cycle(vec![memo.entity(), memo.entity()]))
+        );
+    }
+}
@@ -1,5 +1,7 @@
 /// These are the reference user API systems, patterned after the TC39 proposal.
 pub mod computed;
+pub mod dispose;
 pub mod effect;
 pub mod init;
 pub mod signal;
+pub mod stream;
@@ -1,21 +1,33 @@
-use bevy::{ ecs::world::World, prelude::* };
+use bevy::{ core::FrameCount, ecs::{ storage::SparseSet, world::World }, prelude::* };
 
-use crate::{ arcane_wizardry::*, framework::* };
+use crate::{ arcane_wizardry::*, framework::*, graph::path_to };
 
 // add subscribers to the next running set
 fn add_subs_to_running(
+    source: Entity,
     subs: &[Entity],
     changed: bool,
     triggered: bool,
     next_running: &mut EntitySet,
+    parents: &mut SparseSet<Entity, Entity>,
     world: &mut World
 ) {
     for subscriber in subs.iter() {
         if changed || triggered {
             trace!("-adding subscriber {:?} to running set", subscriber);
             let subscriber = *subscriber;
-            next_running.insert(subscriber, ());
-            let mut subscriber = world.entity_mut(subscriber);
+
+            // skip any subscriber that was despawned since it last subscribed, or can not run
+            let Some(mut subscriber) = world.get_entity_mut(subscriber) else {
+                trace!("-subscriber {:?} is gone", subscriber);
+                continue;
+            };
+            if subscriber.contains::<Faulted>() {
+                trace!("-subscriber {:?} is faulted", subscriber.id());
+                continue;
+            }
+            next_running.insert(subscriber.id(), ());
+            parents.insert(subscriber.id(), source);
             subscriber.insert(Dirty);
 
             // add Triggered to Effects only
@@ -39,16 +51,26 @@ fn merge_running(running: &mut EntitySet, next_running: &mut EntitySet) -> bool
     }
 }
 
+// a `Computed` that was marked dirty outside of this system (e.g. by re-wiring its sources)
+type RewiredParam = (With<ComputedImmutable>, With<Dirty>, Without<ComputeMemo>, Without<Faulted>);
+
 // go through all the signals to send, and if they change or are triggered, mark their subs and
 // subs' subs
 pub fn send_signals(
     world: &mut World,
-    query_signals: &mut QueryState<(Entity, &ImmutableState), With<SendSignal>>
+    query_signals: &mut QueryState<(Entity, &ImmutableState), With<SendSignal>>,
+    query_rewired: &mut QueryState<Entity, RewiredParam>,
+    mut component_id_set: Local<ComponentIdSet>
 ) {
     trace!("SIGNALS");
 
+    let config = world.get_resource::<LazySignalsConfig>().cloned().unwrap_or_default();
+    let frame = world.get_resource::<FrameCount>().map(|frame_count| frame_count.0);
+    world.get_resource_or_insert_with(LazySignalsBudget::default).start_frame(frame);
+
     let mut changed = empty_set();
     let mut next_running = empty_set();
+    let mut parents = SparseSet::<Entity, Entity>::new();
     let mut processed = empty_set();
     let mut running = empty_set();
     let mut triggered = empty_set();
@@ -57,25 +79,20 @@ pub fn send_signals(
     trace!("looking for signals");
     let mut count = 0;
 
-    let mut component_id_set = ComponentIdSet::new();
-    let mut component_info_set = ComponentInfoSet::new();
+    // the buffer is kept between runs, so it only allocates when there are more signals than ever
+    component_id_set.clear();
 
-    // build component id -> info map
+    // build entity -> component id map
     query_signals.iter(world).for_each(|(entity, immutable)| {
         let component_id = immutable.component_id;
         trace!("-found a signal with component ID {:#?}", component_id);
         component_id_set.insert(entity, component_id);
-        if let Some(info) = world.components().get_info(component_id) {
-            component_info_set.insert(component_id, info.clone());
-        }
         count += 1;
     });
     trace!("found {} signals to send", count);
 
-    // build reflect types for merge operation on reflected LazySignalsObservable trait object
-    world.resource_scope(|world, type_registry: Mut<AppTypeRegistry>| {
-        let type_registry = type_registry.read();
-
+    // use the registered vtables for merge operation on LazySignalsObservable trait object
+    world.resource_scope(|world, registry: Mut<LazySignalsRegistry>| {
         for (entity, component_id) in component_id_set.iter() {
             let entity = *entity;
 
@@ -83,14 +100,8 @@ pub fn send_signals(
             let component_id = *component_id;
             let mut signal_to_send = world.entity_mut(entity);
 
-            // use the type_id from the component info
-            let info = component_info_set.get(component_id).unwrap();
-            let type_id = info.type_id().unwrap();
-            // the type_id matches the concrete type of the Signal's generic LazySignalsState
-
-            // it comes from ComponentInfo which is retrieved from the ECS world
-
-            // the component_id is saved when the command to make the concrete Signal runs
+            // the component_id is saved when the command to make the concrete Signal runs, and
+            // that command also registers the vtable for the concrete type under it
 
             // merge the next data value and return a list of subscribers to the change
             // and whether these subscribers should be triggered too
@@ -99,14 +110,15 @@ pub fn send_signals(
                 None,
                 None,
                 &component_id,
-                &type_id,
-                &type_registry,
+                &registry,
                 Box::new(|observable, _args, _target| { observable.merge() })
-            ).unwrap();
+            );
 
-            let subs = result.0;
-            let changed_flag = result.1;
-            let triggered_flag = result.2;
+            // nothing to merge (e.g. the signal was not made by a create command)
+            let Some((subs, changed_flag, triggered_flag)) = result else {
+                signal_to_send.remove::<SendSignal>();
+                continue;
+            };
 
             if changed_flag {
                 changed.insert(entity, ());
@@ -123,9 +135,27 @@ pub fn send_signals(
                 signal_to_send.insert(ValueChanged);
             }
 
+            // so systems can be run only when this signal was triggered (see `signal_triggered`)
+            if triggered_flag {
+                signal_to_send.insert(Triggered);
+            }
+
             // add subscribers to the running set and mark if triggered
             //info!("SUBS for {:#?} are: {:#?}", entity, subs);
-            add_subs_to_running(&subs, changed_flag, triggered_flag, &mut next_running, world);
+            add_subs_to_running(
+                entity,
+                &subs,
+                changed_flag,
+                triggered_flag,
+                &mut next_running,
+                &mut parents,
+                world
+            );
+        }
+
+        // re-wired computeds start running along with the direct subscribers of the signals
+        for rewired in query_rewired.iter(world) {
+            next_running.insert(rewired, ());
         }
 
         // Phase Two: fire notifications up the subscriber tree
@@ -136,6 +166,27 @@ pub fn send_signals(
             count += 1;
             trace!("Sending signals iteration {}", count);
 
+            // guard against feedback loops
+            let rounds = world.resource_mut::<LazySignalsBudget>().count_propagation_round();
+            if rounds > config.max_propagation_rounds {
+                // only report the first time the limit is hit each frame
+                if rounds == config.max_propagation_rounds + 1 {
+                    let chain = path_to(running.indices().next().unwrap(), &parents);
+                    report_loop(LazySignalsLimit::PropagationRounds, chain, config.cut_off, world);
+                }
+
+                if config.cut_off {
+                    // whatever is left will not be computed this time around
+                    for runner in running.indices() {
+                        if let Some(mut subscriber) = world.get_entity_mut(runner) {
+                            subscriber.remove::<Dirty>();
+                        }
+                    }
+                    running.clear();
+                    break;
+                }
+            }
+
             // get an item from the running set
             for runner in running.indices() {
                 // add the item to the processed set
@@ -154,8 +205,7 @@ pub fn send_signals(
                         trace!("-marked memo {:?} for computation", runner);
 
                         let component_id = subscriber.get::<ImmutableState>().unwrap().component_id;
-                        let type_id = subscriber.get::<ComputedImmutable>().unwrap().result_type;
-                        trace!("--got component_id {:?} and type_id {:?}", component_id, type_id);
+                        trace!("--got component_id {:?}", component_id);
 
                         // get a list of subscribers
                         let subs = run_as_observable(
@@ -163,20 +213,21 @@ pub fn send_signals(
                             None,
                             None,
                             &component_id,
-                            &type_id,
-                            &type_registry,
+                            &registry,
                             Box::new(|observable, _args, _target| {
                                 Some((observable.get_subscribers(), false, false))
                             })
                         );
 
                         // computed has its own subscribers, so add those to the next_running set
-                        // and mark triggered if appropriate
+                        // (its value may change) and mark triggered if appropriate
                         add_subs_to_running(
+                            runner,
                             &subs.unwrap().0,
-                            false,
+                            true,
                             triggered.contains(runner),
                             &mut next_running,
+                            &mut parents,
                             world
                         );
                     }
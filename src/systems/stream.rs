@@ -0,0 +1,145 @@
+use bevy::{ core::FrameCount, ecs::world::World, prelude::* };
+
+use crate::{ arcane_wizardry::*, framework::* };
+
+// discard the values delivered by every stream during the last tick, before anything new is merged
+// (they stay readable until then, e.g. by `LazySignals.read_stream` from an `Update` system)
+pub fn drain_streams(
+    world: &mut World,
+    query_streams: &mut QueryState<(Entity, &ImmutableState), With<LazyStream>>,
+    mut component_id_set: Local<ComponentIdSet>
+) {
+    trace!("STREAMS");
+
+    // a tick can be flushed more than once, so only the first pass of a frame drains anything
+    let frame = world.get_resource::<FrameCount>().map(|frame_count| frame_count.0);
+    let mut budget = world.get_resource_or_insert_with(LazySignalsBudget::default);
+    if !budget.start_stream_frame(frame) {
+        return;
+    }
+
+    component_id_set.clear();
+
+    // collapse the query
+    query_streams.iter(world).for_each(|(entity, immutable)| {
+        component_id_set.insert(entity, immutable.component_id);
+    });
+
+    world.resource_scope(|world, registry: Mut<LazySignalsRegistry>| {
+        for (entity, component_id) in component_id_set.iter() {
+            if let Some(mut stream) = world.get_entity_mut(*entity) {
+                run_as_observable(
+                    &mut stream,
+                    None,
+                    None,
+                    component_id,
+                    &registry,
+                    Box::new(|observable, _args, _target| {
+                        observable.drain();
+                        None
+                    })
+                );
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{ Arc, Mutex };
+
+    use bevy::prelude::*;
+
+    use crate::{ lazy_signals_flush_systems, prelude::*, testing::* };
+
+    type Runs = Arc<Mutex<Vec<Vec<u32>>>>;
+
+    // a stream and every `Vec` an effect got from it, one per run
+    fn watched_stream(app: &mut App) -> (StreamHandle<u32>, Runs) {
+        let runs = Runs::default();
+        let world = app.world_mut();
+        let mut commands = world.commands();
+        let stream = LazySignals.stream::<u32>(&mut commands);
+        let effect_runs = runs.clone();
+        LazySignals.effect::<(Vec<u32>,)>(
+            move |(values,), _world| {
+                effect_runs.lock().unwrap().push(values);
+                None
+            },
+            (stream,),
+            vec![],
+            &mut commands
+        );
+        update(app);
+        runs.lock().unwrap().clear();
+        (stream, runs)
+    }
+
+    fn send_all(stream: StreamHandle<u32>, values: &[u32], app: &mut App) {
+        let mut commands = app.world_mut().commands();
+        for value in values {
+            LazySignals.send_to_stream(stream, *value, &mut commands);
+        }
+    }
+
+    #[test]
+    fn sends_in_one_tick_arrive_together() {
+        let mut app = app();
+        let (stream, runs) = watched_stream(&mut app);
+
+        send_all(stream, &[1, 2, 3], &mut app);
+        update(&mut app);
+
+        assert_eq!(*runs.lock().unwrap(), vec![vec![1, 2, 3]]);
+    }
+
+    #[test]
+    fn stream_is_empty_on_the_next_tick() {
+        let mut app = app();
+        let (stream, runs) = watched_stream(&mut app);
+
+        send_all(stream, &[1, 2], &mut app);
+        update(&mut app);
+        update(&mut app);
+
+        assert!(LazySignals.read_stream::<u32>(stream, app.world()).is_empty());
+        assert_eq!(runs.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn values_are_readable_after_the_batch() {
+        #[derive(Resource, Default)]
+        struct Read(Vec<u32>);
+
+        let mut app = app();
+        app.init_resource::<Read>();
+        let (stream, _) = watched_stream(&mut app);
+        app.add_systems(Update, move |world: &World, mut commands: Commands| {
+            let values = LazySignals.read_stream::<u32>(stream, world);
+            commands.insert_resource(Read(values));
+        });
+
+        send_all(stream, &[4, 5], &mut app);
+        update(&mut app);
+        assert_eq!(app.world().resource::<Read>().0, vec![4, 5]);
+
+        update(&mut app);
+        assert!(app.world().resource::<Read>().0.is_empty());
+    }
+
+    #[test]
+    fn values_merged_by_an_earlier_flush_are_drained() {
+        let mut app = app();
+        app.add_systems(First, lazy_signals_flush_systems());
+        let (stream, _) = watched_stream(&mut app);
+
+        // merged in `First`, then flushed again (without anything new) in `PreUpdate`
+        send_all(stream, &[1, 2], &mut app);
+        update(&mut app);
+        assert_eq!(LazySignals.read_stream::<u32>(stream, app.world()), vec![1, 2]);
+
+        send_all(stream, &[3], &mut app);
+        update(&mut app);
+        assert_eq!(LazySignals.read_stream::<u32>(stream, app.world()), vec![3]);
+    }
+}
@@ -0,0 +1,32 @@
+use std::time::{ Duration, Instant };
+
+use bevy::prelude::*;
+
+use crate::prelude::*;
+
+/// App with just enough of Bevy to run the `LazySignalsPlugin`.
+pub fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, LazySignalsPlugin));
+    app
+}
+
+/// Apply the commands queued on the `World` of the app, then run one frame.
+pub fn update(app: &mut App) {
+    app.world_mut().flush();
+    app.update();
+}
+
+/// Run frames until the given time has passed, e.g. to let the tasks of actions finish.
+pub fn update_for(app: &mut App, duration: Duration) {
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        update(app);
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Take the loop events sent since last time.
+pub fn loops_detected(app: &mut App) -> Vec<LazySignalsLoopDetected> {
+    app.world_mut().resource_mut::<Events<LazySignalsLoopDetected>>().drain().collect()
+}